@@ -0,0 +1,29 @@
+#version 120
+precision lowp float;
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform lowp vec2 inv_dsize;
+
+uniform lowp vec2 dithdsize;
+
+
+const lowp float ditherPattern[4] = float[4](
+	1.00, 0.50,
+	0.25, 0.75
+);
+
+void main() {
+	vec4 res = texture2D(Texture, uv) * 0.3;
+	for (float dir = -1.0; dir < 1.1; dir += 2.0) {
+		res += texture2D(Texture, uv + inv_dsize*vec2(dir, 0.0))*0.1;
+		res += texture2D(Texture, uv + inv_dsize*vec2(0.0, dir))*0.1;
+		res += texture2D(Texture, uv + inv_dsize*vec2(dir, dir))*0.075;
+		res += texture2D(Texture, uv + inv_dsize*vec2(dir, -dir))*0.075;
+	}
+	lowp vec2 pp = vec2(uv * dithdsize);
+	pp = floor(pp);
+
+	gl_FragColor = res * color + 0.90*ditherPattern[int(mod(pp.x, 2.0) + mod(pp.y, 2.0)*2.0)] / (255.0);
+}
@@ -0,0 +1,11 @@
+#version 100
+precision lowp float;
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+
+void main() {
+	vec4 res = texture2D(Texture, uv) * color;
+	gl_FragColor = res;
+}
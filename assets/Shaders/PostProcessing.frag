@@ -0,0 +1,45 @@
+#version 120
+precision lowp float;
+varying lowp vec4 color;
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+
+float square(float x) {return x*x;}
+
+const lowp float VIN_AM = 0.45;
+const lowp float BRIGHTNESS = 1.1;
+uniform lowp vec2 screenSize;
+const lowp float ditherPattern[4] = float[4](
+	1.00, 0.50,
+	0.25, 0.75
+);
+
+const lowp float sharpAm = 0.2;
+
+void main() {
+	float vin = 1.0 - VIN_AM*square(uv.x*2.0-1.0);
+	vin *= 1.0 - VIN_AM*square(uv.y*2.0-1.0);
+
+	vec4 res = texture2D(Texture, uv) * color;
+
+	vec2 inv_size = 2/screenSize;
+	vec3 sharpColor = vec3(0.0, 0.0, 0.0);
+	for (float i = -1; i <= 1.5; i+=2.0) {
+		sharpColor += texture2D(Texture, uv + inv_size * vec2(0.0, i)).rgb * 0.1875;
+		sharpColor += texture2D(Texture, uv + inv_size * vec2(i, 0.0)).rgb * 0.1875;
+		sharpColor += texture2D(Texture, uv + inv_size * vec2(i, i)).rgb * 0.0625;
+		sharpColor += texture2D(Texture, uv + inv_size * vec2(-i, i)).rgb * 0.0625;
+	}
+	res.rgb -= (sharpColor - res.rgb) * sharpAm;
+	res.rgb = res.rgb + res.rgb * res.rgb;
+	res.rgb *= 0.5 * vin;
+
+	lowp vec2 pp = vec2(uv * screenSize);
+	pp = floor(pp);
+
+	
+	
+	gl_FragColor = res * BRIGHTNESS + 0.90*ditherPattern[int(mod(pp.x, 2.0) + mod(pp.y, 2.0)*2.0)] / 255.0;
+
+}
@@ -0,0 +1,318 @@
+// packs a folder of sprite pngs into one atlas and writes the sprite regions for the asset manifest.
+// cargo run --bin atlas_pack -- <sprite folder> <atlas png> [options]
+//   --id <texture id>    id the regions use, the atlas file name by default
+//   --padding <px>       empty space between sprites, 1 by default
+//   --extrude <px>       repeats the edge pixels of every frame outwards so
+//                        filtering doesn't pull in the frames next to it, 1 by default
+//   --width <px>         widest the atlas can get, 256 by default
+//   --linear             linear filtering instead of nearest
+// the sprite name is the file name. animations can be packed as one strip with the frame
+// count in the name, walk@4.png is 4 frames next to each other and partical@4x3.png is a
+// grid of 4 by 3, the region is the first frame like the manifest expects. every frame gets
+// its own extruded edge, so the region has a gap of twice the extrusion between frames.
+// the regions go in a .par file next to the atlas, add it to Assets.par with include = <file>;
+
+use std::fs;
+
+use macroquad::prelude::*;
+
+struct SpriteImage {
+	name: String,
+	image: Image,
+	frames: UVec2,
+	// where the first frame goes, not counting its extruded edge
+	pos: UVec2,
+}
+
+impl SpriteImage {
+	fn frame_dim(&self) -> UVec2 {
+		uvec2(self.image.width as u32 / self.frames.x, self.image.height as u32 / self.frames.y)
+	}
+
+	// the frames with their extruded edges
+	fn packed_dim(&self, extrude: u32) -> UVec2 {
+		(self.frame_dim() + UVec2::splat(extrude * 2)) * self.frames
+	}
+}
+
+struct Settings {
+	id: String,
+	padding: u32,
+	extrude: u32,
+	max_width: u32,
+	linear: bool,
+}
+
+fn main() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	if args.len() < 2 {
+		println!("usage: atlas_pack <sprite folder> <atlas png> [--id name] [--padding px] [--extrude px] [--width px] [--linear]");
+		std::process::exit(1);
+	}
+	let folder = args[0].trim_end_matches('/');
+	let atlas_path = &args[1];
+	let settings = match read_settings(atlas_path, &args[2..]) {
+		Ok(s) => s,
+		Err(e) => {
+			println!("{}", e);
+			std::process::exit(1);
+		}
+	};
+
+	let mut sprites = match load_sprites(folder) {
+		Ok(s) => s,
+		Err(e) => {
+			println!("{}", e);
+			std::process::exit(1);
+		}
+	};
+	if sprites.is_empty() {
+		println!("No pngs in {}", folder);
+		std::process::exit(1);
+	}
+
+	let size = pack(&mut sprites, &settings);
+	let atlas = draw_atlas(&sprites, size, settings.extrude);
+	if let Some((atlas_folder, _)) = atlas_path.rsplit_once('/') {
+		if let Err(e) = fs::create_dir_all(atlas_folder) {
+			println!("Could not make {}: {}", atlas_folder, e);
+			std::process::exit(1);
+		}
+	}
+	// export_png flips the rows because it's meant for screen grabs
+	flip_rows(&atlas).export_png(atlas_path);
+
+	let regions_path = match atlas_path.rsplit_once('.') {
+		Some((stem, _)) => format!("{}.par", stem),
+		None => format!("{}.par", atlas_path),
+	};
+	if let Err(e) = fs::write(&regions_path, write_regions(folder, atlas_path, &sprites, &settings)) {
+		println!("Could not write {}: {}", regions_path, e);
+		std::process::exit(1);
+	}
+
+	println!("Packed {} sprites into {} ({}x{}), regions are in {}", sprites.len(), atlas_path, size.x, size.y, regions_path);
+}
+
+fn read_settings(atlas_path: &str, args: &[String]) -> Result<Settings, String> {
+	let file_name = atlas_path.rsplit_once('/').map_or(atlas_path, |(_, f)| f);
+	let mut settings = Settings {
+		id: file_name.split('.').next().unwrap_or(file_name).to_lowercase(),
+		padding: 1,
+		extrude: 1,
+		max_width: 256,
+		linear: false,
+	};
+
+	let mut i = 0;
+	while i < args.len() {
+		let value = args.get(i + 1);
+		let number = || -> Result<u32, String> {
+			value.and_then(|v| v.parse().ok()).ok_or(format!("{} needs a number", args[i]))
+		};
+		match args[i].as_str() {
+			"--id" => settings.id = value.ok_or("--id needs a name")?.clone(),
+			"--padding" => settings.padding = number()?,
+			"--extrude" => settings.extrude = number()?,
+			"--width" => settings.max_width = number()?,
+			"--linear" => {
+				settings.linear = true;
+				i += 1;
+				continue;
+			}
+			arg => return Err(format!("Unknown option {}", arg)),
+		}
+		i += 2;
+	}
+	Ok(settings)
+}
+
+fn load_sprites(folder: &str) -> Result<Vec<SpriteImage>, String> {
+	let entries = fs::read_dir(folder).map_err(|e| format!("Could not read {}: {}", folder, e))?;
+	let mut paths: Vec<_> = entries.flatten()
+		.map(|e| e.path())
+		.filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("png")))
+		.collect();
+	paths.sort();
+
+	let mut sprites = Vec::new();
+	for path in paths {
+		let bytes = fs::read(&path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+		let image = Image::from_file_with_format(&bytes, Some(ImageFormat::Png))
+			.map_err(|e| format!("Could not decode {}: {}", path.display(), e))?;
+		let stem = path.file_stem().map_or(String::new(), |s| s.to_string_lossy().to_string());
+		let (name, frames) = parse_frames(&stem);
+		if !(image.width as u32).is_multiple_of(frames.x) || !(image.height as u32).is_multiple_of(frames.y) {
+			return Err(format!("{} doesn't split into {}x{} frames", path.display(), frames.x, frames.y));
+		}
+		sprites.push(SpriteImage {name, image, frames, pos: UVec2::ZERO});
+	}
+	Ok(sprites)
+}
+
+// walk@4 is 4 frames across, partical@4x3 is 4 across and 3 down
+fn parse_frames(stem: &str) -> (String, UVec2) {
+	let Some((name, frames)) = stem.rsplit_once('@') else {
+		return (stem.to_string(), UVec2::ONE);
+	};
+	let (x, y) = frames.split_once('x').unwrap_or((frames, "1"));
+	match (x.parse::<u32>(), y.parse::<u32>()) {
+		(Ok(x), Ok(y)) if x > 0 && y > 0 => (name.to_string(), uvec2(x, y)),
+		_ => (stem.to_string(), UVec2::ONE),
+	}
+}
+
+// shelf packing, tallest sprites first. returns the atlas size
+fn pack(sprites: &mut [SpriteImage], settings: &Settings) -> UVec2 {
+	sprites.sort_by(|a, b| b.image.height.cmp(&a.image.height).then(b.image.width.cmp(&a.image.width)).then(a.name.cmp(&b.name)));
+
+	let widest = sprites.iter().map(|s| s.packed_dim(settings.extrude).x + settings.padding).max().unwrap_or(0);
+	let max_width = settings.max_width.max(widest + settings.padding);
+
+	let mut cursor = UVec2::splat(settings.padding);
+	let mut shelf_height = 0;
+	let mut width = 0;
+	for sprite in sprites.iter_mut() {
+		let cell = sprite.packed_dim(settings.extrude) + UVec2::splat(settings.padding);
+		if cursor.x + cell.x > max_width {
+			cursor = uvec2(settings.padding, cursor.y + shelf_height);
+			shelf_height = 0;
+		}
+		sprite.pos = cursor + UVec2::splat(settings.extrude);
+		cursor.x += cell.x;
+		shelf_height = shelf_height.max(cell.y);
+		width = width.max(cursor.x);
+	}
+	uvec2(width, cursor.y + shelf_height)
+}
+
+fn draw_atlas(sprites: &[SpriteImage], size: UVec2, extrude: u32) -> Image {
+	let mut atlas = Image::gen_image_color(size.x as u16, size.y as u16, BLANK);
+	let atlas_width = size.x as i32;
+	let extrude = extrude as i32;
+	for sprite in sprites {
+		let image_width = sprite.image.width as i32;
+		let dim = sprite.frame_dim().as_ivec2();
+		let stride = dim + IVec2::splat(extrude * 2);
+		let src_data = sprite.image.get_image_data();
+		let dest_data = atlas.get_image_data_mut();
+		for frame_y in 0..sprite.frames.y as i32 {
+			for frame_x in 0..sprite.frames.x as i32 {
+				let frame = ivec2(frame_x, frame_y);
+				for y in -extrude..dim.y + extrude {
+					for x in -extrude..dim.x + extrude {
+						// pixels outside the frame copy its nearest edge pixel
+						let src = frame * dim + ivec2(x, y).clamp(IVec2::ZERO, dim - IVec2::ONE);
+						let dest = sprite.pos.as_ivec2() + frame * stride + ivec2(x, y);
+						dest_data[(dest.y * atlas_width + dest.x) as usize] = src_data[(src.y * image_width + src.x) as usize];
+					}
+				}
+			}
+		}
+	}
+	atlas
+}
+
+fn flip_rows(image: &Image) -> Image {
+	let row = image.width as usize * 4;
+	let mut flipped = image.clone();
+	for (dest, src) in flipped.bytes.chunks_mut(row).zip(image.bytes.chunks(row).rev()) {
+		dest.copy_from_slice(src);
+	}
+	flipped
+}
+
+fn write_regions(folder: &str, atlas_path: &str, sprites: &[SpriteImage], settings: &Settings) -> String {
+	let file_name = atlas_path.rsplit_once('/').map_or(atlas_path, |(_, f)| f);
+	let mut res = format!("# packed by atlas_pack from {}, packing again overwrites this file\r\n", folder);
+	res.push_str(&format!("texture.{} = {}, {};\r\n\r\n", settings.id, file_name, if settings.linear {"linear"} else {"nearest"}));
+
+	let mut sorted: Vec<&SpriteImage> = sprites.iter().collect();
+	sorted.sort_by(|a, b| a.name.cmp(&b.name));
+	for sprite in sorted {
+		let frame = sprite.frame_dim();
+		res.push_str(&format!("sprite.{} = {}, {}, {}, {}, {}", sprite.name, settings.id, sprite.pos.x, sprite.pos.y, frame.x, frame.y));
+		if sprite.frames != UVec2::ONE && settings.extrude > 0 {
+			res.push_str(&format!(", {}", settings.extrude * 2));
+		}
+		res.push_str(";\r\n");
+	}
+	res
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// every pixel a different colour, so a pixel copied from the wrong place shows up
+	fn sprite(name: &str, size: UVec2, frames: UVec2) -> SpriteImage {
+		let mut image = Image::gen_image_color(size.x as u16, size.y as u16, BLANK);
+		for y in 0..size.y {
+			for x in 0..size.x {
+				image.set_pixel(x, y, Color::from_rgba((x * 16) as u8, (y * 16) as u8, 128, 255));
+			}
+		}
+		SpriteImage {name: name.to_string(), image, frames, pos: UVec2::ZERO}
+	}
+
+	fn settings(max_width: u32) -> Settings {
+		Settings {id: String::from("test"), padding: 1, extrude: 1, max_width, linear: false}
+	}
+
+	fn packed() -> (Vec<SpriteImage>, UVec2) {
+		let mut sprites = vec![
+			sprite("dot", uvec2(2, 2), UVec2::ONE),
+			sprite("walk", uvec2(8, 4), uvec2(2, 1)),
+			sprite("tall", uvec2(6, 10), UVec2::ONE),
+		];
+		let size = pack(&mut sprites, &settings(24));
+		(sprites, size)
+	}
+
+	#[test]
+	fn sprites_go_on_shelves_tallest_first() {
+		let (sprites, size) = packed();
+		let names: Vec<&str> = sprites.iter().map(|s| s.name.as_str()).collect();
+		assert_eq!(names, ["tall", "walk", "dot"]);
+		// every frame is a cell with its extruded edge, then the padding
+		assert_eq!(sprites[0].pos, uvec2(2, 2));
+		assert_eq!(sprites[1].pos, uvec2(11, 2));
+		// doesn't fit after walk, so it starts the next shelf under tall
+		assert_eq!(sprites[2].pos, uvec2(2, 15));
+		assert_eq!(size, uvec2(23, 19));
+	}
+
+	#[test]
+	fn every_frame_gets_its_own_extruded_edge() {
+		let (sprites, size) = packed();
+		let atlas = draw_atlas(&sprites, size, 1);
+		for sprite in &sprites {
+			let dim = sprite.frame_dim().as_ivec2();
+			for frame_y in 0..sprite.frames.y as i32 {
+				for frame_x in 0..sprite.frames.x as i32 {
+					let frame = ivec2(frame_x, frame_y);
+					for y in -1..dim.y + 1 {
+						for x in -1..dim.x + 1 {
+							let src = frame * dim + ivec2(x, y).clamp(IVec2::ZERO, dim - IVec2::ONE);
+							let dest = sprite.pos.as_ivec2() + frame * (dim + IVec2::splat(2)) + ivec2(x, y);
+							assert_eq!(atlas.get_pixel(dest.x as u32, dest.y as u32), sprite.image.get_pixel(src.x as u32, src.y as u32),
+								"{} frame {} at {}", sprite.name, frame, ivec2(x, y));
+						}
+					}
+				}
+			}
+		}
+		// the padding stays empty
+		assert_eq!(atlas.get_pixel(0, 0), BLANK);
+		assert_eq!(atlas.get_pixel(9, 5), BLANK);
+	}
+
+	#[test]
+	fn regions_have_the_gap_between_frames() {
+		let (sprites, _) = packed();
+		let regions = write_regions("sprites", "atlas.png", &sprites, &settings(24));
+		assert!(regions.contains("sprite.walk = test, 11, 2, 4, 4, 2;"));
+		assert!(regions.contains("sprite.tall = test, 2, 2, 6, 10;"));
+		assert!(regions.contains("sprite.dot = test, 2, 15, 2, 2;"));
+	}
+}
\ No newline at end of file
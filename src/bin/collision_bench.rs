@@ -0,0 +1,137 @@
+// times the bullet collisions done every frame with plain loops against the spatial hash the game uses.
+// cargo run --release --bin collision_bench -- [frames]
+// the level is a fixed size with some enemies and breakable walls, only the bullet count goes up
+
+#[allow(dead_code)]
+#[path = "../collision.rs"]
+mod collision;
+#[allow(dead_code)]
+#[path = "../spatial_hash.rs"]
+mod spatial_hash;
+// the tests of the modules above share its random numbers
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../go_helpers.rs"]
+mod go_helpers;
+
+use std::time::Instant;
+
+use macroquad::prelude::*;
+use macroquad::rand::gen_range;
+
+use collision::*;
+use spatial_hash::*;
+
+const WORLD_SIZE: Vec2 = vec2(1600.0, 800.0);
+const GRID_SIZE: f32 = 32.0;
+const ENEMY_COUNT: usize = 200;
+const BREAKABLE_COUNT: usize = 300;
+const BULLET_COUNTS: [usize; 5] = [100, 1000, 2500, 5000, 10000];
+const BULLET_SPEED: f32 = 100.0;
+const DT: f32 = 1.0 / 60.0;
+
+struct Bullet {
+	coll: CollCircle,
+	motion: Vec2,
+}
+
+struct Scene {
+	enemies: Vec<CollBox>,
+	breakable: Vec<CollBox>,
+	bullets: Vec<Bullet>,
+}
+
+fn main() {
+	let frames = std::env::args().nth(1).and_then(|f| f.parse().ok()).unwrap_or(60);
+
+	println!("{} enemies, {} breakable walls, {} frames each", ENEMY_COUNT, BREAKABLE_COUNT, frames);
+	println!("{:>8} {:>14} {:>14} {:>8}", "bullets", "loops ms/frame", "grid ms/frame", "speedup");
+	for count in BULLET_COUNTS {
+		let (brute_time, brute_hits) = run(count, frames, brute_force_frame);
+		let (grid_time, grid_hits) = run(count, frames, spatial_hash_frame());
+		// both have to find exactly the same things or the numbers mean nothing
+		assert_eq!(brute_hits, grid_hits, "the grid found different hits than the loops");
+
+		println!("{:>8} {:>14.3} {:>14.3} {:>7.1}x", count, brute_time, grid_time, brute_time / grid_time);
+	}
+}
+
+// runs the same scene for every method, returns the average frame time and every hit it found
+fn run<F>(bullet_count: usize, frames: usize, mut frame: F) -> (f64, Vec<(usize, usize)>) where F: FnMut(&mut Scene) -> Vec<(usize, usize)> {
+	let mut scene = create_scene(bullet_count);
+	let mut hits = Vec::new();
+
+	let start = Instant::now();
+	for _ in 0..frames {
+		for bullet in scene.bullets.iter_mut() {
+			// bullets leaving the level come back on the other side so the count stays the same
+			let pos = bullet.coll.pos + bullet.motion * DT;
+			bullet.coll.pos = vec2(pos.x.rem_euclid(WORLD_SIZE.x), pos.y.rem_euclid(WORLD_SIZE.y));
+		}
+		hits.append(&mut frame(&mut scene));
+	}
+	let time = start.elapsed().as_secs_f64() * 1000.0 / frames as f64;
+
+	hits.sort_unstable();
+	(time, hits)
+}
+
+fn create_scene(bullet_count: usize) -> Scene {
+	macroquad::rand::srand(7);
+	let random_pos = || vec2(gen_range(0.0, WORLD_SIZE.x), gen_range(0.0, WORLD_SIZE.y));
+
+	Scene {
+		enemies: (0..ENEMY_COUNT).map(|_| collbox!(random_pos(), Vec2::splat(4.0))).collect(),
+		breakable: (0..BREAKABLE_COUNT).map(|_| collbox!((random_pos() / 16.0).floor() * 16.0, Vec2::splat(8.0))).collect(),
+		bullets: (0..bullet_count).map(|_| Bullet {
+			coll: collcircle!(random_pos(), 2.0),
+			motion: Vec2::from_angle(gen_range(0.0, std::f32::consts::TAU)) * BULLET_SPEED,
+		}).collect(),
+	}
+}
+
+// what GameWorld::update did before, every enemy and wall against every bullet.
+// hits are (thing, bullet) with the breakable walls after the enemies
+fn brute_force_frame(scene: &mut Scene) -> Vec<(usize, usize)> {
+	let mut hits = Vec::new();
+	for (e, enemy) in scene.enemies.iter().enumerate() {
+		for (b, bullet) in scene.bullets.iter().enumerate() {
+			if check_circle_box_intersection(&bullet.coll, enemy) {
+				hits.push((e, b));
+			}
+		}
+	}
+	for (w, wall) in scene.breakable.iter().enumerate() {
+		for (b, bullet) in scene.bullets.iter().enumerate() {
+			if check_circle_box_intersection(&bullet.coll, wall) {
+				hits.push((ENEMY_COUNT + w, b));
+			}
+		}
+	}
+	hits
+}
+
+// the grid lives between frames like in the game, so this times keeping it up to date too
+fn spatial_hash_frame() -> impl FnMut(&mut Scene) -> Vec<(usize, usize)> {
+	let mut grid = SpatialHash::new(GRID_SIZE);
+	move |scene| {
+		grid.update_all(&scene.bullets, |b| b.coll.to_box());
+
+		let mut hits = Vec::new();
+		for (e, enemy) in scene.enemies.iter().enumerate() {
+			grid.query(enemy, |b, _| {
+				if check_circle_box_intersection(&scene.bullets[b].coll, enemy) {
+					hits.push((e, b));
+				}
+			});
+		}
+		for (w, wall) in scene.breakable.iter().enumerate() {
+			grid.query(wall, |b, _| {
+				if check_circle_box_intersection(&scene.bullets[b].coll, wall) {
+					hits.push((ENEMY_COUNT + w, b));
+				}
+			});
+		}
+		hits
+	}
+}
@@ -0,0 +1,75 @@
+// reports which strings a translation is missing.
+// cargo run --bin lang_report -- French
+// with no language given every table in assets/Lang is checked
+
+#[allow(dead_code)]
+#[path = "../parser.rs"]
+mod parser;
+
+#[allow(dead_code)]
+#[path = "../localization.rs"]
+mod localization;
+
+#[allow(dead_code)]
+#[path = "../vfs.rs"]
+mod vfs;
+
+use std::fs;
+
+use localization::*;
+use parser::StringParser;
+
+fn main() {
+	let lang_folder = "assets/Lang";
+	let base = match fs::read_to_string(create_language_path(DEFAULT_LANGUAGE)) {
+		Ok(s) => StringParser::new(&s),
+		Err(e) => {
+			println!("Could not read the {} table: {}", DEFAULT_LANGUAGE, e);
+			std::process::exit(1);
+		}
+	};
+
+	let mut languages: Vec<String> = std::env::args().skip(1).collect();
+	if languages.is_empty() {
+		if let Ok(entries) = fs::read_dir(lang_folder) {
+			for entry in entries.flatten() {
+				let path = entry.path();
+				if path.extension().is_some_and(|e| e == "par") {
+					if let Some(name) = path.file_stem() {
+						languages.push(name.to_string_lossy().to_string());
+					}
+				}
+			}
+		}
+		languages.retain(|l| l != DEFAULT_LANGUAGE);
+		languages.sort();
+	}
+
+	let mut any_missing = false;
+	for language in languages {
+		let translation = match fs::read_to_string(create_language_path(&language)) {
+			Ok(s) => StringParser::new(&s),
+			Err(e) => {
+				println!("{}: could not read the table: {}", language, e);
+				any_missing = true;
+				continue;
+			}
+		};
+
+		let missing = find_missing_keys(&base, &translation);
+		let unused = find_unused_keys(&base, &translation);
+		let total = base.get_names().len();
+		println!("{}: {} of {} strings translated", language, total - missing.len(), total);
+		for key in &missing {
+			println!("  missing: {}", key);
+		}
+		for key in &unused {
+			println!("  not in {}: {}", DEFAULT_LANGUAGE, key);
+		}
+		any_missing = any_missing || !missing.is_empty();
+	}
+
+	if any_missing {
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,51 @@
+// packs everything in assets and packs into one archive the game can read instead of the loose files.
+// cargo run --bin pack_assets -- [archive path]
+// the archive is assets.pak by default, which is where --features embed_assets looks for it
+
+#[allow(dead_code)]
+#[path = "../vfs.rs"]
+mod vfs;
+
+use std::fs;
+use std::path::Path;
+
+fn main() {
+	let archive_path = std::env::args().nth(1).unwrap_or(String::from(vfs::ARCHIVE_NAME));
+
+	let mut files = Vec::new();
+	if let Err(e) = collect_files(Path::new("assets"), "assets", &mut files) {
+		println!("{}", e);
+		std::process::exit(1);
+	}
+	// level packs go in too, so they can be picked in game
+	if Path::new(vfs::PACK_FOLDER).is_dir() {
+		if let Err(e) = collect_files(Path::new(vfs::PACK_FOLDER), vfs::PACK_FOLDER, &mut files) {
+			println!("{}", e);
+			std::process::exit(1);
+		}
+	}
+	files.sort_by(|a, b| a.0.cmp(&b.0));
+
+	let archive = vfs::write_archive(&files);
+	if let Err(e) = fs::write(&archive_path, &archive) {
+		println!("Could not write {}: {}", archive_path, e);
+		std::process::exit(1);
+	}
+	println!("Packed {} files into {} ({} bytes)", files.len(), archive_path, archive.len());
+}
+
+// paths in the archive use / like the paths the game loads with
+fn collect_files(folder: &Path, name: &str, files: &mut Vec<(String, Vec<u8>)>) -> Result<(), String> {
+	let entries = fs::read_dir(folder).map_err(|e| format!("Could not read {}: {}", folder.display(), e))?;
+	for entry in entries.flatten() {
+		let path = entry.path();
+		let entry_name = format!("{}/{}", name, entry.file_name().to_string_lossy());
+		if path.is_dir() {
+			collect_files(&path, &entry_name, files)?;
+		} else {
+			let data = fs::read(&path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+			files.push((entry_name, data));
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,144 @@
+// level wiring. switches send events on the channel the level file gave them, GameWorld hands
+// them out once a frame and everything on a channel only looks at whether it's on
+
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ChannelEvent {
+	// pressure plates, the channel is on while any of them are held down
+	Pressed(u32),
+	Released(u32),
+	// levers flip the channel
+	Toggled(u32),
+	// keys, an unlocked channel stays on
+	Unlocked(u32),
+}
+
+#[derive(Clone, Copy, Default)]
+struct ChannelState {
+	held: u32,
+	toggled: bool,
+	unlocked: bool,
+}
+
+impl ChannelState {
+	fn is_on(&self) -> bool {
+		self.held > 0 || self.toggled || self.unlocked
+	}
+}
+
+#[derive(Default)]
+pub struct Channels {
+	states: HashMap<u32, ChannelState>,
+	queue: Vec<ChannelEvent>,
+}
+
+impl Channels {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn clear(&mut self) {
+		self.states.clear();
+		self.queue.clear();
+	}
+
+	// nothing changes until the next dispatch
+	pub fn send(&mut self, event: ChannelEvent) {
+		self.queue.push(event);
+	}
+
+	// applies everything sent since the last dispatch, gives the channels that turned on or off
+	pub fn dispatch(&mut self) -> Vec<(u32, bool)> {
+		let mut changed: Vec<(u32, bool)> = Vec::new();
+		for event in std::mem::take(&mut self.queue) {
+			let channel = match event {
+				ChannelEvent::Pressed(c) | ChannelEvent::Released(c) | ChannelEvent::Toggled(c) | ChannelEvent::Unlocked(c) => c,
+			};
+			let state = self.states.entry(channel).or_default();
+			let was_on = state.is_on();
+			match event {
+				ChannelEvent::Pressed(_) => state.held += 1,
+				ChannelEvent::Released(_) => state.held = state.held.saturating_sub(1),
+				ChannelEvent::Toggled(_) => state.toggled = !state.toggled,
+				ChannelEvent::Unlocked(_) => state.unlocked = true,
+			}
+
+			if state.is_on() != was_on {
+				// a channel that flipped back in the same frame didn't change
+				match changed.iter().position(|(c, _)| *c == channel) {
+					Some(i) => {changed.remove(i);},
+					None => changed.push((channel, state.is_on())),
+				}
+			}
+		}
+		changed
+	}
+
+	pub fn is_on(&self, channel: u32) -> bool {
+		self.states.get(&channel).is_some_and(|state| state.is_on())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn plates_hold_a_channel_while_any_are_down() {
+		let mut channels = Channels::new();
+		channels.send(ChannelEvent::Pressed(1));
+		channels.send(ChannelEvent::Pressed(1));
+		assert_eq!(channels.dispatch(), vec![(1, true)]);
+
+		// the second plate still holds it
+		channels.send(ChannelEvent::Released(1));
+		assert!(channels.dispatch().is_empty());
+		assert!(channels.is_on(1));
+
+		channels.send(ChannelEvent::Released(1));
+		assert_eq!(channels.dispatch(), vec![(1, false)]);
+		assert!(!channels.is_on(1));
+
+		// more releases than presses don't go below nothing
+		channels.send(ChannelEvent::Released(1));
+		channels.send(ChannelEvent::Pressed(1));
+		assert_eq!(channels.dispatch(), vec![(1, true)]);
+	}
+
+	#[test]
+	fn flipping_back_in_the_same_frame_is_no_change() {
+		let mut channels = Channels::new();
+		channels.send(ChannelEvent::Pressed(1));
+		channels.send(ChannelEvent::Released(1));
+		channels.send(ChannelEvent::Toggled(2));
+		channels.send(ChannelEvent::Toggled(2));
+		channels.send(ChannelEvent::Toggled(3));
+		assert_eq!(channels.dispatch(), vec![(3, true)]);
+		assert!(!channels.is_on(1) && !channels.is_on(2));
+
+		// three flips is one
+		for _ in 0..3 {
+			channels.send(ChannelEvent::Toggled(2));
+		}
+		assert_eq!(channels.dispatch(), vec![(2, true)]);
+		assert!(channels.dispatch().is_empty());
+	}
+
+	#[test]
+	fn unlocked_channels_stay_on() {
+		let mut channels = Channels::new();
+		channels.send(ChannelEvent::Unlocked(4));
+		channels.send(ChannelEvent::Toggled(4));
+		assert_eq!(channels.dispatch(), vec![(4, true)]);
+
+		channels.send(ChannelEvent::Toggled(4));
+		channels.send(ChannelEvent::Pressed(4));
+		channels.send(ChannelEvent::Released(4));
+		assert!(channels.dispatch().is_empty());
+		assert!(channels.is_on(4));
+
+		channels.clear();
+		assert!(!channels.is_on(4));
+	}
+}
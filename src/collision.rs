@@ -1,5 +1,10 @@
 use macroquad::math::*;
 
+// how far down a box can already be when it lands on a one way platform, the player sinks this far to stay grounded
+const ONE_WAY_TOLERANCE: f32 = 0.05;
+// how high a slope can be and still be walked onto from the side
+const SLOPE_STEP: f32 = 4.0;
+
 
 macro_rules! collbox {
 	(($x:expr, $y:expr), ($w:expr, $h:expr)) => {
@@ -77,41 +82,53 @@ impl CollBox {
 		}
 	}
 
-	pub fn align_edge_x(&mut self, other: &Self) {
-		self.pos.x = other.pos.x -(self.half_dim.x + other.half_dim.x) * f32::signum(other.pos.x - self.pos.x);
+	// motion is which way self was going, it picks the side when both centres are the same
+	pub fn align_edge_x(&mut self, other: &Self, motion: f32) {
+		self.pos.x = other.pos.x -(self.half_dim.x + other.half_dim.x) * edge_side(self.pos.x, other.pos.x, motion);
 	}
-	pub fn align_edge_y(&mut self, other: &Self) {
-		self.pos.y = other.pos.y -(self.half_dim.y + other.half_dim.y) * f32::signum(other.pos.y - self.pos.y);
+	pub fn align_edge_y(&mut self, other: &Self, motion: f32) {
+		self.pos.y = other.pos.y -(self.half_dim.y + other.half_dim.y) * edge_side(self.pos.y, other.pos.y, motion);
 	}
 
-	pub fn collide_x(&mut self, other: &Self) -> bool {
-		if (self.check_intersection(&other)) {
-			self.align_edge_x(&other);
+	pub fn collide_x(&mut self, other: &Self, motion: f32) -> bool {
+		if self.check_intersection(other) {
+			self.align_edge_x(other, motion);
 			true
 		} else{
 			false
 		}
 	}
-	pub fn collide_y(&mut self, other: &Self) -> bool {
-		if (self.check_intersection(&other)) {
-			self.align_edge_y(&other);
+	pub fn collide_y(&mut self, other: &Self, motion: f32) -> bool {
+		if self.check_intersection(other) {
+			self.align_edge_y(other, motion);
 			true
 		} else{
 			false
 		}
 	}
 
-	pub fn collide_x_slice(&mut self, other: &[Self]) -> bool {
+	// one way platforms only stop things coming down onto them, prev_y is where self was before it moved
+	pub fn land_on(&mut self, platform: &Self, prev_y: f32) -> bool {
+		let top = platform.pos.y - platform.half_dim.y;
+		let overlaps_x = (self.pos.x - platform.pos.x).abs() < self.half_dim.x + platform.half_dim.x;
+		if !overlaps_x || prev_y + self.half_dim.y > top + ONE_WAY_TOLERANCE || self.pos.y + self.half_dim.y <= top {
+			return false;
+		}
+		self.pos.y = top - self.half_dim.y;
+		true
+	}
+
+	pub fn collide_x_slice(&mut self, other: &[Self], motion: f32) -> bool {
 		let mut res = false;
 		for i in 0..other.len() {
-			res = self.collide_x(&other[i]) || res;
+			res = self.collide_x(&other[i], motion) || res;
 		}
 		res
 	}
-	pub fn collide_y_slice(&mut self, other: &[Self]) -> bool {
+	pub fn collide_y_slice(&mut self, other: &[Self], motion: f32) -> bool {
 		let mut res = false;
 		for i in 0..other.len() {
-			res = self.collide_y(&other[i]) || res;
+			res = self.collide_y(&other[i], motion) || res;
 		}
 		res
 	}
@@ -119,6 +136,28 @@ impl CollBox {
 	pub fn to_circle(&self) -> CollCircle {
 		CollCircle::new(self.pos, (self.half_dim.x + self.half_dim.y) * 0.5)
 	}
+
+	// where moving by motion first touches other
+	pub fn sweep(&self, motion: Vec2, other: &Self) -> Option<SweepHit> {
+		sweep_point_box(self.pos, motion, &CollBox::new(other.pos, other.half_dim + self.half_dim))
+	}
+
+	// rays starting inside the box don't hit it
+	pub fn raycast(&self, origin: Vec2, dir: Vec2, max_distance: f32) -> Option<RayHit> {
+		let motion = dir.normalize_or_zero() * max_distance;
+		RayHit::from_sweep(origin, motion, sweep_point_box(origin, motion, self))
+	}
+
+	pub fn sweep_slice(&self, motion: Vec2, others: &[Self]) -> Option<SweepHit> {
+		let bounds = swept_bounds(self, motion);
+		let mut res = None;
+		for other in others {
+			if other.check_intersection(&bounds) {
+				res = SweepHit::first(res, self.sweep(motion, other));
+			}
+		}
+		res
+	}
 }
 
 pub struct CollCircle {
@@ -151,6 +190,256 @@ impl CollCircle {
 	pub fn to_box(&self) -> CollBox{
 		CollBox::new(self.pos, Vec2::splat(self.radius))
 	}
+
+	pub fn raycast(&self, origin: Vec2, dir: Vec2, max_distance: f32) -> Option<RayHit> {
+		let motion = dir.normalize_or_zero() * max_distance;
+		RayHit::from_sweep(origin, motion, sweep_point_circle(origin, motion, self.pos, self.radius))
+	}
+
+	pub fn sweep(&self, motion: Vec2, other: &Self) -> Option<SweepHit> {
+		sweep_point_circle(self.pos, motion, other.pos, self.radius + other.radius)
+	}
+
+	pub fn sweep_box(&self, motion: Vec2, cbox: &CollBox) -> Option<SweepHit> {
+		let hit = sweep_point_box(self.pos, motion, &CollBox::new(cbox.pos, cbox.half_dim + Vec2::splat(self.radius)))?;
+		// past the corners of the box the circle touches the corner itself and not the grown box
+		let offset = self.pos + motion * hit.time - cbox.pos;
+		let outside = offset.abs() - cbox.half_dim;
+		if outside.x > 0.0 && outside.y > 0.0 {
+			let corner = cbox.pos + cbox.half_dim * offset.signum();
+			return sweep_point_circle(self.pos, motion, corner, self.radius);
+		}
+		Some(hit)
+	}
+
+	pub fn sweep_box_slice(&self, motion: Vec2, others: &[CollBox]) -> Option<SweepHit> {
+		let bounds = swept_bounds(&self.to_box(), motion);
+		let mut res = None;
+		for other in others {
+			if other.check_intersection(&bounds) {
+				res = SweepHit::first(res, self.sweep_box(motion, other));
+			}
+		}
+		res
+	}
+}
+
+// a tile with a sloped top, solid under the line from the left height to the right height.
+// the heights go up from the bottom of the tile
+#[derive(Clone, Copy)]
+pub struct CollSlope {
+	pub cbox: CollBox,
+	pub left: f32,
+	pub right: f32,
+}
+
+impl CollSlope {
+	pub fn new(cbox: CollBox, left: f32, right: f32) -> Self {
+		Self {cbox, left, right}
+	}
+
+	// the y of the top at x, x is kept inside the tile
+	pub fn surface_y(&self, x: f32) -> f32 {
+		let across = ((x - self.cbox.pos.x + self.cbox.half_dim.x) / (self.cbox.half_dim.x * 2.0)).clamp(0.0, 1.0);
+		self.cbox.pos.y + self.cbox.half_dim.y - (self.left + (self.right - self.left) * across)
+	}
+
+	// a box rests on the highest part of the slope under it
+	pub fn rest_y(&self, other: &CollBox) -> f32 {
+		self.surface_y(other.pos.x - other.half_dim.x).min(self.surface_y(other.pos.x + other.half_dim.x))
+	}
+
+	pub fn contains_point(&self, point: Vec2) -> bool {
+		(point - self.cbox.pos).abs().cmplt(self.cbox.half_dim).all() && point.y > self.surface_y(point.x)
+	}
+
+	// only the high side of a slope is in the way of things next to it, the low side is walked onto
+	pub fn collide_x(&self, other: &mut CollBox, motion: f32) -> bool {
+		let beside = (other.pos.x - self.cbox.pos.x).abs() >= self.cbox.half_dim.x;
+		if !beside || !other.check_intersection(&self.cbox) || other.pos.y + other.half_dim.y <= self.rest_y(other) + SLOPE_STEP {
+			return false;
+		}
+		other.align_edge_x(&self.cbox, motion);
+		true
+	}
+
+	// puts things standing on the slope on top of it, from below it is a full tile.
+	// snap pulls things down onto the top from that far above it, so walking down a slope isn't falling
+	pub fn collide_y(&self, other: &mut CollBox, snap: f32) -> bool {
+		Self::collide_y_slice(other, std::slice::from_ref(self), snap)
+	}
+
+	// where other stands on the slope, None when it's not on it
+	fn landing_y(&self, other: &CollBox, snap: f32) -> Option<f32> {
+		let rest = self.rest_y(other);
+		if other.pos.y + other.half_dim.y < rest - snap {
+			return None;
+		}
+		Some(rest - other.half_dim.y)
+	}
+
+	fn is_below(&self, other: &CollBox) -> bool {
+		other.pos.y > self.cbox.pos.y + self.cbox.half_dim.y
+	}
+
+	fn overlaps_x(&self, other: &CollBox) -> bool {
+		(other.pos.x - self.cbox.pos.x).abs() < other.half_dim.x + self.cbox.half_dim.x
+	}
+
+	pub fn collide_x_slice(other: &mut CollBox, slopes: &[Self], motion: f32) -> bool {
+		let mut res = false;
+		for slope in slopes {
+			res = slope.collide_x(other, motion) || res;
+		}
+		res
+	}
+
+	// something over more than one slope stands on the highest one
+	pub fn collide_y_slice(other: &mut CollBox, slopes: &[Self], snap: f32) -> bool {
+		let mut res = false;
+		let mut landing: Option<f32> = None;
+		for slope in slopes {
+			if !slope.overlaps_x(other) {
+				continue;
+			}
+			if slope.is_below(other) {
+				// below the bottom means the centres are never the same, so motion doesn't matter
+				res = other.collide_y(&slope.cbox, 0.0) || res;
+			} else if let Some(y) = slope.landing_y(other, snap) {
+				landing = Some(landing.map_or(y, |l| l.min(y)));
+			}
+		}
+
+		if let Some(y) = landing {
+			other.pos.y = y;
+			res = true;
+		}
+		res
+	}
+}
+
+// where something moving first touches something else. time goes from 0 at the start of the
+// motion to 1 at the end of it, the normal points away from what was hit.
+// things that already overlap at the start don't count as a hit, so they can still move apart
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SweepHit {
+	pub time: f32,
+	pub normal: Vec2,
+}
+
+impl SweepHit {
+	pub fn first(a: Option<Self>, b: Option<Self>) -> Option<Self> {
+		match (a, b) {
+			(Some(a), Some(b)) => Some(if b.time < a.time {b} else {a}),
+			(a, b) => a.or(b),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+	pub point: Vec2,
+	pub normal: Vec2,
+	pub distance: f32,
+}
+
+impl RayHit {
+	pub fn closest(a: Option<Self>, b: Option<Self>) -> Option<Self> {
+		match (a, b) {
+			(Some(a), Some(b)) => Some(if b.distance < a.distance {b} else {a}),
+			(a, b) => a.or(b),
+		}
+	}
+
+	fn from_sweep(origin: Vec2, motion: Vec2, hit: Option<SweepHit>) -> Option<Self> {
+		hit.map(|hit| Self {
+			point: origin + motion * hit.time,
+			normal: hit.normal,
+			distance: motion.length() * hit.time,
+		})
+	}
+}
+
+// if nothing in walls is between from and to
+pub fn line_of_sight(walls: &[MultiCollider<CollBox>], from: Vec2, to: Vec2) -> bool {
+	MultiCollider::raycast_slice(walls, from, to - from, from.distance(to)).is_none()
+}
+
+// the box around everything a box covers while it moves
+pub fn swept_bounds(cbox: &CollBox, motion: Vec2) -> CollBox {
+	CollBox::new(cbox.pos + motion * 0.5, cbox.half_dim + motion.abs() * 0.5)
+}
+
+// a point moving by motion against a box
+fn sweep_point_box(start: Vec2, motion: Vec2, cbox: &CollBox) -> Option<SweepHit> {
+	let (entry, _, normal) = slab_range(start, motion, cbox)?;
+	if !(0.0..=1.0).contains(&entry) {
+		return None;
+	}
+	Some(SweepHit {time: entry, normal})
+}
+
+// the slab test, when start + motion * time is inside the box as (entry, exit, entry normal).
+// the times aren't limited to 0 to 1, entry is below 0 when start is inside
+fn slab_range(start: Vec2, motion: Vec2, cbox: &CollBox) -> Option<(f32, f32, Vec2)> {
+	let min = cbox.pos - cbox.half_dim;
+	let max = cbox.pos + cbox.half_dim;
+
+	let mut entry = f32::NEG_INFINITY;
+	let mut exit = f32::INFINITY;
+	let mut normal = Vec2::ZERO;
+	for axis in 0..2 {
+		if motion[axis] == 0.0 {
+			// never gets into the box on this axis
+			if start[axis] <= min[axis] || start[axis] >= max[axis] {
+				return None;
+			}
+			continue;
+		}
+
+		let (near, far) = if motion[axis] > 0.0 {(min[axis], max[axis])} else {(max[axis], min[axis])};
+		let near_time = (near - start[axis]) / motion[axis];
+		let far_time = (far - start[axis]) / motion[axis];
+		if near_time > entry {
+			entry = near_time;
+			normal = Vec2::ZERO;
+			normal[axis] = -motion[axis].signum();
+		}
+		exit = exit.min(far_time);
+	}
+
+	// entry == exit only touches an edge or a corner
+	if entry >= exit {
+		return None;
+	}
+	Some((entry, exit, normal))
+}
+
+fn sweep_point_circle(start: Vec2, motion: Vec2, center: Vec2, radius: f32) -> Option<SweepHit> {
+	let offset = start - center;
+	let a = motion.dot(motion);
+	let b = offset.dot(motion);
+	let c = offset.dot(offset) - radius * radius;
+	// not moving, starting inside or moving away
+	if a == 0.0 || c < 0.0 || b >= 0.0 {
+		return None;
+	}
+
+	let discriminant = b * b - a * c;
+	if discriminant <= 0.0 {
+		return None;
+	}
+	let time = (-b - discriminant.sqrt()) / a;
+	if !(0.0..=1.0).contains(&time) {
+		return None;
+	}
+	Some(SweepHit {time, normal: (start + motion * time - center).normalize_or_zero()})
+}
+
+// 1 when to is after from and -1 when it's before. things with the same centre go back the way
+// they came, and before when they weren't moving. signum would put them after for a -0.0 difference
+fn edge_side(from: f32, to: f32, motion: f32) -> f32 {
+	if to > from || (to == from && motion >= 0.0) {1.0} else {-1.0}
 }
 
 pub fn check_circle_box_intersection (circle: &CollCircle, cbox: &CollBox) -> bool {
@@ -227,14 +516,14 @@ impl <TC> MultiCollider<TC> where TC: Collider+Clone {
 
 #[allow(dead_code)]
 impl MultiCollider<CollBox> {
-	pub fn collide_x (&self, other:&mut CollBox) -> bool {
+	pub fn collide_x (&self, other:&mut CollBox, motion: f32) -> bool {
 		if !other.check_intersection(&self.main) {
 			return false;
 		}
 		let mut result = false;
 		for i in 0..self.multi.len() {
 			if other.check_intersection(&self.multi[i]) {
-				other.align_edge_x(&self.multi[i]);
+				other.align_edge_x(&self.multi[i], motion);
 				result = true;
 			}
 		}
@@ -242,16 +531,16 @@ impl MultiCollider<CollBox> {
 		result
 	}
 
-	pub fn collide_x_slice(coll: &mut CollBox, ss: &[Self])-> bool {
+	pub fn collide_x_slice(coll: &mut CollBox, ss: &[Self], motion: f32)-> bool {
 		let mut result = false;
 		for i in 0..ss.len() {
-			result = result|| ss[i].collide_x(&mut *coll);
+			result = ss[i].collide_x(&mut *coll, motion) || result;
 		}
 
 		result
 	}
 
-	pub fn collide_y (&self, other:&mut CollBox) -> bool {
+	pub fn collide_y (&self, other:&mut CollBox, motion: f32) -> bool {
 		if !other.check_intersection(&self.main) {
 			return false;
 		}
@@ -259,7 +548,7 @@ impl MultiCollider<CollBox> {
 		let mut result = false;
 		for i in 0..self.multi.len() {
 			if other.check_intersection(&self.multi[i]) {
-				other.align_edge_y(&self.multi[i]);
+				other.align_edge_y(&self.multi[i], motion);
 				result = true
 			}
 		}
@@ -267,12 +556,79 @@ impl MultiCollider<CollBox> {
 		result
 	}
 
-	pub fn collide_y_slice(coll: &mut CollBox, ss: &[Self])-> bool {
+	// like collide_y but for one way platforms, prev_y is where other was before it moved
+	pub fn collide_y_one_way(&self, other: &mut CollBox, prev_y: f32) -> bool {
+		let moved = CollBox::new(vec2(other.pos.x, (other.pos.y + prev_y) * 0.5), other.half_dim + vec2(0.0, (other.pos.y - prev_y).abs() * 0.5));
+		if !moved.check_intersection(&self.main) {
+			return false;
+		}
+
+		let mut result = false;
+		for platform in &self.multi {
+			result = other.land_on(platform, prev_y) || result;
+		}
+		result
+	}
+
+	pub fn collide_y_one_way_slice(coll: &mut CollBox, ss: &[Self], prev_y: f32) -> bool {
+		let mut result = false;
+		for mc in ss {
+			result = mc.collide_y_one_way(coll, prev_y) || result;
+		}
+		result
+	}
+
+	// the first wall a box moving by motion touches
+	pub fn sweep_box_slice(ss: &[Self], cbox: &CollBox, motion: Vec2) -> Option<SweepHit> {
+		let bounds = swept_bounds(cbox, motion);
+		let mut res = None;
+		for mc in ss {
+			if mc.main.check_intersection(&bounds) {
+				res = SweepHit::first(res, cbox.sweep_slice(motion, &mc.multi));
+			}
+		}
+		res
+	}
+
+	// goes through the grid cells in the order the ray gets to them and stops once the
+	// closest hit is before the next cell
+	pub fn raycast_slice(ss: &[Self], origin: Vec2, dir: Vec2, max_distance: f32) -> Option<RayHit> {
+		let motion = dir.normalize_or_zero() * max_distance;
+		let mut cells: Vec<(f32, &Self)> = ss.iter().filter_map(|mc| {
+			let (entry, exit, _) = slab_range(origin, motion, &mc.main)?;
+			(exit >= 0.0 && entry <= 1.0).then_some((entry.max(0.0) * max_distance, mc))
+		}).collect();
+		cells.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+		let mut res: Option<RayHit> = None;
+		for (entry, mc) in cells {
+			if res.is_some_and(|hit| hit.distance <= entry) {
+				break;
+			}
+			for wall in &mc.multi {
+				res = RayHit::closest(res, wall.raycast(origin, dir, max_distance));
+			}
+		}
+		res
+	}
+
+	pub fn sweep_circle_slice(ss: &[Self], circle: &CollCircle, motion: Vec2) -> Option<SweepHit> {
+		let bounds = swept_bounds(&circle.to_box(), motion);
+		let mut res = None;
+		for mc in ss {
+			if mc.main.check_intersection(&bounds) {
+				res = SweepHit::first(res, circle.sweep_box_slice(motion, &mc.multi));
+			}
+		}
+		res
+	}
+
+	pub fn collide_y_slice(coll: &mut CollBox, ss: &[Self], motion: f32)-> bool {
 		let mut result = false;
 		//let mut in_sectors = 0;
 		//let mut checked = 0;
 		for i in 0..ss.len() {
-			result = result|| ss[i].collide_y(&mut *coll);
+			result = ss[i].collide_y(&mut *coll, motion) || result;
 			/*if ss[i].main.check_intersection(&coll) {
 				in_sectors+=1;
 				checked+=ss[i].read_multi().len();
@@ -296,4 +652,477 @@ pub fn generate_multi_col_grid(offset:Vec2, size:Vec2, num_elements:UVec2) -> Ve
 	}
 
 	result
-}
\ No newline at end of file
+}
+
+// every collider is on one layer, a mask holds the layers it reacts to.
+// a layer is always a single bit, masks can have any number of them.
+// walls, breakable walls and ghost blocks block the movement of things that have them in their mask,
+// everything else only reports contacts
+pub const LAYER_WALL: u32 = 1 << 0;
+pub const LAYER_BREAKABLE: u32 = 1 << 1;
+pub const LAYER_GHOST_BLOCK: u32 = 1 << 2;
+pub const LAYER_PLAYER: u32 = 1 << 3;
+pub const LAYER_ENEMY: u32 = 1 << 4;
+pub const LAYER_BULLET: u32 = 1 << 5;
+pub const LAYER_TRIGGER: u32 = 1 << 6;
+
+// which layers react to which, always both ways round
+#[derive(Clone, Copy, Default)]
+pub struct LayerMatrix {
+	masks: [u32; 32],
+}
+
+#[allow(dead_code)]
+impl LayerMatrix {
+	// nothing reacts to anything
+	pub fn new() -> Self {
+		Self {masks: [0; 32]}
+	}
+
+	// layer reacts to every layer in layers and they to it
+	pub fn set(&mut self, layer: u32, layers: u32, react: bool) {
+		if layer == 0 {
+			return;
+		}
+		for i in 0..32 {
+			let other = 1 << i;
+			if layers & other == 0 {
+				continue;
+			}
+			if react {
+				self.masks[Self::index(layer)] |= other;
+				self.masks[i] |= layer;
+			} else {
+				self.masks[Self::index(layer)] &= !other;
+				self.masks[i] &= !layer;
+			}
+		}
+	}
+
+	// no layer at all reacts to nothing
+	pub fn mask(&self, layer: u32) -> u32 {
+		if layer == 0 {
+			return 0;
+		}
+		self.masks[Self::index(layer)]
+	}
+
+	pub fn reacts(&self, a: u32, b: u32) -> bool {
+		self.mask(a) & b != 0
+	}
+
+	fn index(layer: u32) -> usize {
+		debug_assert!(layer.is_power_of_two(), "{:#b} isn't a single layer", layer);
+		layer.trailing_zeros() as usize
+	}
+}
+
+// the layer and mask of one collider
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CollLayers {
+	pub layer: u32,
+	pub mask: u32,
+}
+
+#[allow(dead_code)]
+impl CollLayers {
+	// starts with everything the matrix lets the layer react to
+	pub fn new(layer: u32, matrix: &LayerMatrix) -> Self {
+		Self {layer, mask: matrix.mask(layer)}
+	}
+
+	pub fn with(self, layers: u32) -> Self {
+		Self {mask: self.mask | layers, ..self}
+	}
+
+	pub fn without(self, layers: u32) -> Self {
+		Self {mask: self.mask & !layers, ..self}
+	}
+
+	pub fn reacts(&self, layers: u32) -> bool {
+		self.mask & layers != 0
+	}
+
+	// both sides have to react for there to be a contact
+	pub fn contact(&self, other: &CollLayers) -> bool {
+		self.reacts(other.layer) && other.reacts(self.layer)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::go_helpers::TestRng as Rng;
+
+	const CASES: usize = 2000;
+	const AREA: f32 = 256.0;
+	const GROUND: f32 = AREA;
+
+	trait CollisionCases {
+		fn snapped(&mut self, low: f32, high: f32) -> f32;
+		fn cbox(&mut self, max_half: f32) -> CollBox;
+		fn pair(&mut self) -> (CollBox, CollBox);
+	}
+
+	impl CollisionCases for Rng {
+		// on quarter pixels so touching edges and shared centres come up a lot, and the maths stays exact
+		fn snapped(&mut self, low: f32, high: f32) -> f32 {
+			(self.range(low, high) * 4.0).round() / 4.0
+		}
+
+		// always inside the area, the grids don't reach any further
+		fn cbox(&mut self, max_half: f32) -> CollBox {
+			let half_dim = vec2(self.snapped(1.0, max_half), self.snapped(1.0, max_half));
+			collbox!(vec2(self.snapped(half_dim.x, AREA - half_dim.x), self.snapped(half_dim.y, AREA - half_dim.y)), half_dim)
+		}
+
+		// a box and something it's likely to overlap, sometimes with the same centre on one or both axes
+		fn pair(&mut self) -> (CollBox, CollBox) {
+			let a = self.cbox(24.0);
+			let mut b = collbox!(a.pos + vec2(self.snapped(-32.0, 32.0), self.snapped(-32.0, 32.0)), vec2(self.snapped(1.0, 24.0), self.snapped(1.0, 24.0)));
+			match self.next() % 4 {
+				0 => b.pos.x = a.pos.x,
+				1 => b.pos.y = a.pos.y,
+				2 => b.pos = a.pos,
+				_ => {}
+			}
+			(a, b)
+		}
+	}
+
+	fn grid_with(walls: &[CollBox]) -> Vec<MultiCollider<CollBox>> {
+		let mut grid = generate_multi_col_grid(Vec2::ZERO, Vec2::splat(64.0), uvec2(4, 4));
+		for wall in walls {
+			MultiCollider::add_if_intersect_slice(&mut grid, wall);
+		}
+		grid
+	}
+
+	// columns standing on the ground, anything with its centre above theirs gets pushed up
+	fn columns(rng: &mut Rng) -> Vec<CollBox> {
+		(0..12).map(|_| {
+			let (top, half_width) = (rng.snapped(100.0, 250.0), rng.snapped(2.0, 16.0));
+			collbox!(vec2(rng.snapped(half_width, AREA - half_width), (top + GROUND) * 0.5), vec2(half_width, (GROUND - top) * 0.5))
+		}).collect()
+	}
+
+	// bars sticking out of the left edge, anything with its centre right of theirs gets pushed right
+	fn bars(rng: &mut Rng) -> Vec<CollBox> {
+		(0..12).map(|_| {
+			let (right, half_height) = (rng.snapped(100.0, 250.0), rng.snapped(2.0, 16.0));
+			collbox!(vec2(right * 0.5, rng.snapped(half_height, AREA - half_height)), vec2(right * 0.5, half_height))
+		}).collect()
+	}
+
+	fn overlaps_any(cbox: &CollBox, walls: &[CollBox]) -> bool {
+		walls.iter().any(|wall| wall.check_intersection(cbox))
+	}
+
+	#[test]
+	fn align_edge_leaves_boxes_touching() {
+		let mut rng = Rng(1);
+		for case in 0..CASES {
+			let (a, b) = rng.pair();
+
+			let mut moved = a;
+			moved.align_edge_x(&b, 0.0);
+			assert_eq!((moved.pos.x - b.pos.x).abs(), a.half_dim.x + b.half_dim.x, "case {case}");
+			assert_eq!(moved.pos.y, a.pos.y, "case {case}");
+			assert!(!moved.check_intersection(&b), "case {case}");
+
+			let mut moved = a;
+			moved.align_edge_y(&b, 0.0);
+			assert_eq!((moved.pos.y - b.pos.y).abs(), a.half_dim.y + b.half_dim.y, "case {case}");
+			assert_eq!(moved.pos.x, a.pos.x, "case {case}");
+			assert!(!moved.check_intersection(&b), "case {case}");
+		}
+	}
+
+	#[test]
+	fn align_edge_keeps_the_side_it_was_on() {
+		let mut rng = Rng(2);
+		for case in 0..CASES {
+			let (a, b) = rng.pair();
+			let mut moved = a;
+			let motion = rng.snapped(-1.0, 1.0);
+			moved.align_edge_x(&b, motion);
+			moved.align_edge_y(&b, motion);
+			if a.pos.x != b.pos.x {
+				assert_eq!((moved.pos.x - b.pos.x).signum(), (a.pos.x - b.pos.x).signum(), "case {case}");
+			}
+			if a.pos.y != b.pos.y {
+				assert_eq!((moved.pos.y - b.pos.y).signum(), (a.pos.y - b.pos.y).signum(), "case {case}");
+			}
+		}
+	}
+
+	#[test]
+	fn align_edge_with_the_same_centre() {
+		let wall = collbox!(vec2(0.0, 0.0), vec2(8.0, 8.0));
+		for pos in [vec2(0.0, 0.0), vec2(-0.0, -0.0)] {
+			let mut cbox = collbox!(pos, vec2(4.0, 4.0));
+			cbox.align_edge_x(&wall, 0.0);
+			cbox.align_edge_y(&wall, 0.0);
+			assert_eq!(cbox.pos, vec2(-12.0, -12.0));
+		}
+
+		let mut cbox = collbox!(vec2(0.0, 0.0), vec2(4.0, 4.0));
+		cbox.align_edge_x(&collbox!(vec2(-0.0, 0.0), vec2(8.0, 8.0)), 0.0);
+		assert_eq!(cbox.pos.x, -12.0);
+	}
+
+	#[test]
+	fn same_centre_goes_back_the_way_it_came() {
+		let wall = collbox!(vec2(20.0, 30.0), vec2(8.0, 8.0));
+		for (motion, side) in [(3.0, -1.0), (-3.0, 1.0), (0.0, -1.0)] {
+			let mut cbox = collbox!(wall.pos, vec2(4.0, 4.0));
+			assert!(cbox.collide_x(&wall, motion));
+			assert_eq!(cbox.pos, vec2(20.0 + 12.0 * side, 30.0), "motion {motion}");
+
+			let mut cbox = collbox!(wall.pos, vec2(4.0, 4.0));
+			assert!(cbox.collide_y(&wall, motion));
+			assert_eq!(cbox.pos, vec2(20.0, 30.0 + 12.0 * side), "motion {motion}");
+
+			let grid = grid_with(&[wall]);
+			let mut cbox = collbox!(wall.pos, vec2(4.0, 4.0));
+			assert!(MultiCollider::collide_x_slice(&mut cbox, &grid, motion));
+			assert_eq!(cbox.pos, vec2(20.0 + 12.0 * side, 30.0), "motion {motion}");
+		}
+	}
+
+	#[test]
+	fn collide_leaves_no_overlap() {
+		let mut rng = Rng(3);
+		for case in 0..CASES {
+			let (a, b) = rng.pair();
+			let overlapped = a.check_intersection(&b);
+
+			let mut moved = a;
+			assert_eq!(moved.collide_x(&b, 0.0), overlapped, "case {case}");
+			assert!(!moved.check_intersection(&b), "case {case}");
+			if !overlapped {
+				assert_eq!(moved.pos, a.pos, "case {case}");
+			}
+
+			let mut moved = a;
+			assert_eq!(moved.collide_y(&b, 0.0), overlapped, "case {case}");
+			assert!(!moved.check_intersection(&b), "case {case}");
+			if !overlapped {
+				assert_eq!(moved.pos, a.pos, "case {case}");
+			}
+		}
+	}
+
+	#[test]
+	fn collide_y_slice_lands_on_the_lowest_column_top() {
+		let mut rng = Rng(4);
+		for case in 0..CASES {
+			let cols = columns(&mut rng);
+			let start = collbox!(vec2(rng.snapped(0.0, AREA), rng.snapped(80.0, 170.0)), vec2(rng.snapped(1.0, 16.0), rng.snapped(1.0, 8.0)));
+			let tops_under = cols.iter()
+				.filter(|col| col.check_intersection(&collbox!(vec2(start.pos.x, GROUND * 0.5), vec2(start.half_dim.x, GROUND))))
+				.map(|col| col.pos.y - col.half_dim.y)
+				.filter(|&top| top < start.pos.y + start.half_dim.y);
+
+			let mut cbox = start;
+			let hit = cbox.collide_y_slice(&cols, 1.0);
+			assert_eq!(hit, overlaps_any(&start, &cols), "case {case}");
+			assert!(!overlaps_any(&cbox, &cols), "case {case}");
+			assert_eq!(cbox.pos.x, start.pos.x, "case {case}");
+			match tops_under.reduce(f32::min) {
+				Some(top) => assert_eq!(cbox.pos.y + cbox.half_dim.y, top, "case {case}"),
+				None => assert_eq!(cbox.pos, start.pos, "case {case}"),
+			}
+		}
+	}
+
+	#[test]
+	fn collide_x_slice_leaves_no_overlap() {
+		let mut rng = Rng(5);
+		for case in 0..CASES {
+			let walls = bars(&mut rng);
+			let start = collbox!(vec2(rng.snapped(130.0, 240.0), rng.snapped(0.0, AREA)), vec2(rng.snapped(1.0, 8.0), rng.snapped(1.0, 16.0)));
+
+			let mut cbox = start;
+			assert_eq!(cbox.collide_x_slice(&walls, -1.0), overlaps_any(&start, &walls), "case {case}");
+			assert!(!overlaps_any(&cbox, &walls), "case {case}");
+			assert!(cbox.pos.x >= start.pos.x, "case {case}");
+		}
+	}
+
+	#[test]
+	fn multi_collider_intersection_matches_brute_force() {
+		let mut rng = Rng(6);
+		for case in 0..200 {
+			let walls: Vec<CollBox> = (0..20).map(|_| rng.cbox(24.0)).collect();
+			let grid = grid_with(&walls);
+			for _ in 0..20 {
+				let cbox = rng.cbox(24.0);
+				assert_eq!(MultiCollider::check_intersection_slice(&grid, &cbox), overlaps_any(&cbox, &walls), "case {case}");
+			}
+		}
+	}
+
+	#[test]
+	fn multi_collider_collide_matches_brute_force() {
+		let mut rng = Rng(7);
+		for case in 0..CASES {
+			let cols = columns(&mut rng);
+			let grid = grid_with(&cols);
+			let start = collbox!(vec2(rng.snapped(0.0, AREA), rng.snapped(80.0, 170.0)), vec2(rng.snapped(1.0, 40.0), rng.snapped(1.0, 8.0)));
+			let (mut brute, mut multi) = (start, start);
+			assert_eq!(MultiCollider::collide_y_slice(&mut multi, &grid, 1.0), brute.collide_y_slice(&cols, 1.0), "case {case}");
+			assert_eq!(multi.pos, brute.pos, "case {case}");
+			assert!(!overlaps_any(&multi, &cols), "case {case}");
+
+			let walls = bars(&mut rng);
+			let grid = grid_with(&walls);
+			let start = collbox!(vec2(rng.snapped(130.0, 240.0), rng.snapped(0.0, AREA)), vec2(rng.snapped(1.0, 8.0), rng.snapped(1.0, 40.0)));
+			let (mut brute, mut multi) = (start, start);
+			assert_eq!(MultiCollider::collide_x_slice(&mut multi, &grid, -1.0), brute.collide_x_slice(&walls, -1.0), "case {case}");
+			assert_eq!(multi.pos, brute.pos, "case {case}");
+			assert!(!overlaps_any(&multi, &walls), "case {case}");
+		}
+	}
+
+	#[test]
+	fn multi_collider_sweeps_and_raycasts_match_brute_force() {
+		let mut rng = Rng(8);
+		for case in 0..200 {
+			let walls: Vec<CollBox> = (0..20).map(|_| rng.cbox(16.0)).collect();
+			let grid = grid_with(&walls);
+			for _ in 0..20 {
+				let cbox = rng.cbox(8.0);
+				let motion = vec2(rng.range(-128.0, 128.0), rng.range(-128.0, 128.0));
+				assert_eq!(
+					MultiCollider::sweep_box_slice(&grid, &cbox, motion).map(|hit| hit.time),
+					cbox.sweep_slice(motion, &walls).map(|hit| hit.time),
+					"case {case}"
+				);
+
+				let circle = collcircle!(cbox.pos, cbox.half_dim.x);
+				assert_eq!(
+					MultiCollider::sweep_circle_slice(&grid, &circle, motion).map(|hit| hit.time),
+					circle.sweep_box_slice(motion, &walls).map(|hit| hit.time),
+					"case {case}"
+				);
+
+				let brute = walls.iter().fold(None, |res, wall| RayHit::closest(res, wall.raycast(cbox.pos, motion, 200.0)));
+				assert_eq!(MultiCollider::raycast_slice(&grid, cbox.pos, motion, 200.0).map(|hit| hit.distance), brute.map(|hit| hit.distance), "case {case}");
+			}
+		}
+	}
+
+	#[test]
+	fn circle_box_intersection() {
+		let cbox = collbox!(vec2(0.0, 0.0), vec2(8.0, 4.0));
+		assert!(check_circle_box_intersection(&collcircle!(vec2(0.0, 0.0), 1.0), &cbox));
+		assert!(check_circle_box_intersection(&collcircle!(vec2(9.0, 0.0), 2.0), &cbox));
+		// touching isn't overlapping
+		assert!(!check_circle_box_intersection(&collcircle!(vec2(10.0, 0.0), 2.0), &cbox));
+		// close to the corner on both axes but not to the corner itself
+		assert!(!check_circle_box_intersection(&collcircle!(vec2(9.5, 5.5), 2.0), &cbox));
+		assert!(check_circle_box_intersection(&collcircle!(vec2(9.0, 5.0), 2.0), &cbox));
+	}
+
+	#[test]
+	fn circle_box_intersection_matches_the_distance() {
+		let mut rng = Rng(9);
+		for case in 0..CASES {
+			let cbox = rng.cbox(16.0);
+			let circle = collcircle!(cbox.pos + vec2(rng.range(-32.0, 32.0), rng.range(-32.0, 32.0)), rng.range(0.5, 12.0));
+			let distance = ((circle.pos - cbox.pos).abs() - cbox.half_dim).max(Vec2::ZERO).length();
+			assert_eq!(check_circle_box_intersection(&circle, &cbox), distance < circle.radius, "case {case}");
+			// a circle overlapping a box always overlaps the box around it
+			if check_circle_box_intersection(&circle, &cbox) {
+				assert!(circle.to_box().check_intersection(&cbox), "case {case}");
+			}
+		}
+	}
+
+	#[test]
+	fn layer_matrix_is_always_both_ways_round() {
+		let mut rng = Rng(11);
+		// what every pair should be, checked against after every change
+		let mut expected = [[false; 32]; 32];
+		let mut matrix = LayerMatrix::new();
+		for case in 0..CASES {
+			let layer = (rng.next() % 32) as usize;
+			let layers = (rng.next() & rng.next()) as u32;
+			let react = rng.next().is_multiple_of(2);
+			matrix.set(1 << layer, layers, react);
+			for other in (0..32).filter(|i| layers & (1 << i) != 0) {
+				expected[layer][other] = react;
+				expected[other][layer] = react;
+			}
+
+			for a in 0..32 {
+				for b in 0..32 {
+					assert_eq!(matrix.reacts(1 << a, 1 << b), expected[a][b], "case {case}: {a} and {b}");
+					assert_eq!(matrix.reacts(1 << a, 1 << b), matrix.reacts(1 << b, 1 << a), "case {case}: {a} and {b}");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn no_layer_reacts_to_nothing() {
+		let mut matrix = LayerMatrix::new();
+		matrix.set(LAYER_ENEMY, LAYER_BULLET, true);
+		matrix.set(0, LAYER_ENEMY, true);
+		assert_eq!(matrix.mask(0), 0);
+		assert!(!matrix.reacts(0, LAYER_ENEMY) && !matrix.reacts(LAYER_ENEMY, 0));
+		assert_eq!(matrix.mask(LAYER_ENEMY), LAYER_BULLET);
+		assert_eq!(CollLayers::new(0, &matrix).mask, 0);
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic]
+	fn a_layer_is_a_single_bit() {
+		LayerMatrix::new().mask(LAYER_ENEMY | LAYER_BULLET);
+	}
+
+	#[test]
+	fn contacts_need_both_masks() {
+		let mut matrix = LayerMatrix::new();
+		matrix.set(LAYER_ENEMY, LAYER_BULLET | LAYER_TRIGGER, true);
+		let enemy = CollLayers::new(LAYER_ENEMY, &matrix);
+		let bullet = CollLayers::new(LAYER_BULLET, &matrix);
+		assert!(enemy.contact(&bullet) && bullet.contact(&enemy));
+		assert!(!enemy.without(LAYER_BULLET).contact(&bullet));
+		assert!(!bullet.contact(&enemy.without(LAYER_BULLET)));
+		assert!(enemy.contact(&CollLayers::new(LAYER_TRIGGER, &matrix)));
+
+		// only one side reacting isn't enough
+		let wall = CollLayers::new(LAYER_WALL, &matrix);
+		assert!(!enemy.with(LAYER_WALL).contact(&wall));
+		assert!(enemy.with(LAYER_WALL).contact(&wall.with(LAYER_ENEMY)));
+	}
+
+	#[test]
+	fn multi_col_grid_covers_the_area_once() {
+		let offset = vec2(-16.0, 8.0);
+		let size = vec2(32.0, 48.0);
+		let count = uvec2(5, 3);
+		let grid = generate_multi_col_grid(offset, size, count);
+		assert_eq!(grid.len(), 15);
+
+		// x on the outside
+		for (i, mc) in grid.iter().enumerate() {
+			let cell = vec2((i as u32 / count.y) as f32, (i as u32 % count.y) as f32);
+			assert_eq!(mc.main.pos, offset + size * (cell + 0.5));
+			assert_eq!(mc.main.half_dim, size * 0.5);
+			assert!(mc.read_multi().is_empty());
+		}
+
+		let mut rng = Rng(10);
+		let end = offset + size * count.as_vec2();
+		for _ in 0..CASES {
+			let point = collbox!(vec2(rng.range(offset.x, end.x), rng.range(offset.y, end.y)), Vec2::splat(0.01));
+			assert_eq!(grid.iter().filter(|mc| mc.main.check_intersection(&point)).count(), 1, "{}", point.pos);
+		}
+		let outside = collbox!(end + 1.0, Vec2::splat(0.5));
+		assert!(grid.iter().all(|mc| !mc.main.check_intersection(&outside)));
+	}
+}
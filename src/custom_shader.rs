@@ -1,6 +1,7 @@
 use std::default;
 
 use crate::options::*;
+use crate::vfs;
 use macroquad::prelude::*;
 use miniquad::*;
 
@@ -12,146 +13,84 @@ pub fn make_mult_blend() -> BlendState {
 
 
 
-pub const DEF_VERTEX: &str = r"#version 100
-precision lowp float;
-attribute vec3 position;
-attribute vec2 texcoord;
-attribute vec4 color0;
+pub const DEF_VERTEX: &str = include_str!("../assets/Shaders/Default.vert");
 
-varying lowp vec2 uv;
-varying lowp vec4 color;
+pub const BRIGHTNESS_VERTEX: &str = include_str!("../assets/Shaders/Brightness.vert");
 
-uniform mat4 Model;
-uniform mat4 Projection;
+pub const DEF_FRAGMENT: &str = include_str!("../assets/Shaders/Default.frag");
 
-void main() {
-	gl_Position = Projection * Model * vec4(position, 1);
+pub const BLUR_SHADER: &str = include_str!("../assets/Shaders/Blur.frag");
 
-	color = color0 / 255.0;
+pub const POST_PROCESSING: &str = include_str!("../assets/Shaders/PostProcessing.frag");
 
-	uv = texcoord;
-}
-";
-
-pub const BRIGHTNESS_VERTEX: &str = r"#version 100
-precision lowp float;
-attribute vec3 position;
-attribute vec2 texcoord;
-attribute vec4 color0;
-
-varying lowp vec2 uv;
-varying lowp vec4 color;
-
-uniform mat4 Model;
-uniform mat4 Projection;
-
-uniform float brightness;
-
-void main() {
-	gl_Position = Projection * Model * vec4(position, 1);
-
-	color = color0 / 255.0;
-	color *= brightness;
+pub const SHADER_FOLDER: &str = "assets/Shaders";
 
-	uv = texcoord;
+// the shader sources the materials get built from, the built in ones are the files in assets/Shaders
+// at the time of compiling, load reads them again so they can be changed while the game runs
+pub struct ShaderSources {
+	pub def_vertex: String,
+	pub brightness_vertex: String,
+	pub def_fragment: String,
+	pub blur: String,
+	pub post_processing: String,
 }
-";
 
-pub const DEF_FRAGMENT: &str = r"#version 100
-precision lowp float;
-varying vec4 color;
-varying vec2 uv;
-
-uniform sampler2D Texture;
+impl ShaderSources {
+	pub fn built_in() -> Self {
+		Self {
+			def_vertex: DEF_VERTEX.to_string(),
+			brightness_vertex: BRIGHTNESS_VERTEX.to_string(),
+			def_fragment: DEF_FRAGMENT.to_string(),
+			blur: BLUR_SHADER.to_string(),
+			post_processing: POST_PROCESSING.to_string(),
+		}
+	}
 
-void main() {
-	vec4 res = texture2D(Texture, uv) * color;
-	gl_FragColor = res;
+	pub async fn load() -> Result<Self, String> {
+		async fn load_source(name: &str) -> Result<String, String> {
+			let path = format!("{}/{}", SHADER_FOLDER, name);
+			vfs::load_string(&path).await.map_err(|_| format!("Could not read {}", path))
+		}
+
+		Ok(Self {
+			def_vertex: load_source("Default.vert").await?,
+			brightness_vertex: load_source("Brightness.vert").await?,
+			def_fragment: load_source("Default.frag").await?,
+			blur: load_source("Blur.frag").await?,
+			post_processing: load_source("PostProcessing.frag").await?,
+		})
+	}
 }
-";
-
-pub const BLUR_SHADER: &str =r"#version 120
-precision lowp float;
-varying vec4 color;
-varying vec2 uv;
-
-uniform sampler2D Texture;
-uniform lowp vec2 inv_dsize;
-
-uniform lowp vec2 dithdsize;
 
+pub struct Materials {
+	pub light: Material,
+	pub shadow: Material,
+	pub screen: Material,
+}
 
-const lowp float ditherPattern[4] = float[4](
-	1.00, 0.50,
-	0.25, 0.75
-);
-
-void main() {
-	vec4 res = texture2D(Texture, uv) * 0.3;
-	for (float dir = -1.0; dir < 1.1; dir += 2.0) {
-		res += texture2D(Texture, uv + inv_dsize*vec2(dir, 0.0))*0.1;
-		res += texture2D(Texture, uv + inv_dsize*vec2(0.0, dir))*0.1;
-		res += texture2D(Texture, uv + inv_dsize*vec2(dir, dir))*0.075;
-		res += texture2D(Texture, uv + inv_dsize*vec2(dir, -dir))*0.075;
+impl Materials {
+	// fails with the compiler output of the first shader that doesn't compile
+	pub fn new(sources: &ShaderSources) -> Result<Self, String> {
+		Ok(Self {
+			light: create_light_mat(sources).map_err(|e| format!("Light shader: {}", e))?,
+			shadow: create_shadow_mat(sources).map_err(|e| format!("Shadow shader: {}", e))?,
+			screen: create_screen_mat(sources).map_err(|e| format!("Screen shader: {}", e))?,
+		})
 	}
-	lowp vec2 pp = vec2(uv * dithdsize);
-	pp = floor(pp);
-
-	gl_FragColor = res * color + 0.90*ditherPattern[int(mod(pp.x, 2.0) + mod(pp.y, 2.0)*2.0)] / (255.0);
 }
-";
-
-pub const POST_PROCESSING: &str = r"#version 120
-precision lowp float;
-varying lowp vec4 color;
-varying lowp vec2 uv;
-
-uniform sampler2D Texture;
-
-float square(float x) {return x*x;}
-
-const lowp float VIN_AM = 0.45;
-const lowp float BRIGHTNESS = 1.1;
-uniform lowp vec2 screenSize;
-const lowp float ditherPattern[4] = float[4](
-	1.00, 0.50,
-	0.25, 0.75
-);
 
-const lowp float sharpAm = 0.2;
-
-void main() {
-	float vin = 1.0 - VIN_AM*square(uv.x*2.0-1.0);
-	vin *= 1.0 - VIN_AM*square(uv.y*2.0-1.0);
-
-	vec4 res = texture2D(Texture, uv) * color;
-
-	vec2 inv_size = 2/screenSize;
-	vec3 sharpColor = vec3(0.0, 0.0, 0.0);
-	for (float i = -1; i <= 1.5; i+=2.0) {
-		sharpColor += texture2D(Texture, uv + inv_size * vec2(0.0, i)).rgb * 0.1875;
-		sharpColor += texture2D(Texture, uv + inv_size * vec2(i, 0.0)).rgb * 0.1875;
-		sharpColor += texture2D(Texture, uv + inv_size * vec2(i, i)).rgb * 0.0625;
-		sharpColor += texture2D(Texture, uv + inv_size * vec2(-i, i)).rgb * 0.0625;
+// the compiler output is only in the debug print of the error
+fn shader_error(e: macroquad::Error) -> String {
+	match e {
+		macroquad::Error::ShaderError(e) => format!("{:?}", e),
+		e => e.to_string(),
 	}
-	res.rgb -= (sharpColor - res.rgb) * sharpAm;
-	res.rgb = res.rgb + res.rgb * res.rgb;
-	res.rgb *= 0.5 * vin;
-
-	lowp vec2 pp = vec2(uv * screenSize);
-	pp = floor(pp);
-
-	
-	
-	gl_FragColor = res * BRIGHTNESS + 0.90*ditherPattern[int(mod(pp.x, 2.0) + mod(pp.y, 2.0)*2.0)] / 255.0;
-
 }
-";
 
 const MAX_LIGHT_BRIGHTNESS:f32 = 1.0;
 
-pub fn create_light_mat () -> Material {
-	let mat = load_material(ShaderSource::Glsl { vertex: BRIGHTNESS_VERTEX, fragment: DEF_FRAGMENT }, MaterialParams{
+pub fn create_light_mat (sources: &ShaderSources) -> Result<Material, String> {
+	let mat = load_material(ShaderSource::Glsl { vertex: &sources.brightness_vertex, fragment: &sources.def_fragment }, MaterialParams{
 		pipeline_params: PipelineParams{
 			cull_face: CullFace::Nothing,
 			depth_test: Comparison::Always,
@@ -161,13 +100,13 @@ pub fn create_light_mat () -> Material {
 		},
 		uniforms: vec!(("brightness".to_string(), UniformType::Float1)),
 		..Default::default()
-	}).unwrap();
+	}).map_err(shader_error)?;
 	mat.set_uniform("brightness", 0.55f32);
-	mat
+	Ok(mat)
 }
 
-pub fn create_shadow_mat () -> Material {
-	let mat = load_material(ShaderSource::Glsl { vertex: DEF_VERTEX, fragment: BLUR_SHADER }, MaterialParams{
+pub fn create_shadow_mat (sources: &ShaderSources) -> Result<Material, String> {
+	let mat = load_material(ShaderSource::Glsl { vertex: &sources.def_vertex, fragment: &sources.blur }, MaterialParams{
 		pipeline_params: PipelineParams{
 			cull_face: CullFace::Nothing,
 			depth_test: Comparison::Always,
@@ -178,15 +117,15 @@ pub fn create_shadow_mat () -> Material {
 		uniforms: vec![("inv_dsize".to_string(), UniformType::Float2), ("dithdsize".to_string(), UniformType::Float2)],
 
 		..Default::default()
-	}).unwrap();
+	}).map_err(shader_error)?;
 	mat.set_uniform("inv_dsize", 1.5*vec2(1.0/(SHADOWMAP_DIM.x as f32), 1.0/(SHADOWMAP_DIM.y as f32)));
 	mat.set_uniform("dithdsize", SCREEN_DIM.as_vec2());
-	mat
+	Ok(mat)
 }
 
-pub fn create_screen_mat() -> Material {
+pub fn create_screen_mat(sources: &ShaderSources) -> Result<Material, String> {
 	let mat = load_material(
-	ShaderSource::Glsl { vertex: DEF_VERTEX, fragment: POST_PROCESSING },
+	ShaderSource::Glsl { vertex: &sources.def_vertex, fragment: &sources.post_processing },
 	MaterialParams{
 		pipeline_params: PipelineParams{
 			cull_face: CullFace::Nothing,
@@ -196,7 +135,7 @@ pub fn create_screen_mat() -> Material {
 		},
 		uniforms: vec![("screenSize".to_string(), UniformType::Float2)],
 		..Default::default()
-	}).unwrap();
+	}).map_err(shader_error)?;
 	mat.set_uniform("screenSize", SCREEN_DIM.as_vec2());
-	mat
+	Ok(mat)
 }
\ No newline at end of file
@@ -1,68 +1,549 @@
 use macroquad::prelude::*;
-use std::collections::{hash_map, HashMap};
+use std::collections::HashMap;
+
+use crate::options::*;
+use crate::parser::*;
+use crate::vfs;
+
+const WAVE_SPEED: f32 = 6.0;
+const WAVE_HEIGHT: f32 = 1.5;
+const WAVE_STEP: f32 = 0.6;
+const SHAKE_SPEED: f32 = 20.0;
+const SHAKE_AMOUNT: f32 = 0.8;
+
+// stops fallback loops like "a | A" and "A | a" from running forever
+const MAX_FALLBACK_DEPTH: usize = 8;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TextAlign {
+	Left,
+	Center,
+	Right,
+}
+
+impl TextAlign {
+	pub fn from_name(name: &str) -> Self {
+		match name.trim().to_lowercase().as_str() {
+			"center" | "centre" => TextAlign::Center,
+			"right" => TextAlign::Right,
+			_ => TextAlign::Left,
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TextEffect {
+	Still,
+	Wave,
+	Shake,
+}
+
+#[derive(Clone, Copy)]
+pub struct TextStyle {
+	pub color: Option<Color>,
+	pub effect: TextEffect,
+}
+
+impl TextStyle {
+	pub const PLAIN: Self = Self { color: None, effect: TextEffect::Still };
+}
+
+#[derive(Clone, Copy)]
+pub struct RGlyph {
+	pub rect: Rect,
+	pub advance: f32,
+	pub offset: Vec2,
+	pub style: TextStyle,
+}
+
 pub struct RFont {
 	texture: Texture2D,
 	text_hash: HashMap<char, Rect>,
+	advances: HashMap<char, f32>,
+	offsets: HashMap<char, Vec2>,
+	kerning: HashMap<(char, char), f32>,
+	fallbacks: HashMap<char, char>,
 	def_rect: Rect,
 	spacing: Vec2
 }
 
 
 
+#[allow(dead_code)]
 impl RFont {
 	pub fn new(texture: Texture2D, text_hash: HashMap<char, Rect>, def_rect: Rect, spacing: Vec2) -> Self{
 		Self {
-			texture: texture, text_hash: text_hash, def_rect: def_rect, spacing: spacing
+			texture: texture, text_hash: text_hash, advances: HashMap::new(), offsets: HashMap::new(), kerning: HashMap::new(), fallbacks: HashMap::new(), def_rect: def_rect, spacing: spacing
 		}
 	}
 
-	pub fn output_text(&self, text: &str) -> Vec<Rect> {
-		let mut otp = Vec::with_capacity(text.len());
-		for c in text.chars() {
-			otp.push(match self.text_hash.get(&c) {
-				Some(v) => v.clone(),
-				None => self.def_rect
-			});
+	// builds a font from a description file, see assets/Images/Font.par for the format
+	pub fn from_description(texture: Texture2D, desc: &str) -> Self {
+		let parser = StringParser::new(desc);
+		let mut font = Self::new(texture, HashMap::new(), Rect::new(0.0, 0.0, 1.0, 1.0), vec2(7.0, 9.0));
+		font.apply_description(&parser, true);
+		font
+	}
+
+	// builds a font from an AngelCode BMFont text file. only the first page is used,
+	// so texture should be the image named by bmfont_page_file
+	pub fn from_bmfont(texture: Texture2D, fnt: &str) -> Self {
+		let mut font = Self::new(texture, HashMap::new(), Rect::new(0.0, 0.0, 0.0, 0.0), vec2(8.0, 16.0));
+		let mut space_advance = None;
+
+		for line in fnt.lines() {
+			let (tag, values) = parse_bmfont_line(line);
+			let get = |key: &str| values.get(key).map_or(0.0, |v| parse_float(v));
+			match tag {
+				"common" => font.spacing = vec2(get("lineHeight") * 0.5, get("lineHeight")),
+
+				"char" => {
+					if get("page") != 0.0 {continue;}
+					let c = match char::from_u32(get("id") as u32) {
+						Some(c) => c,
+						None => continue,
+					};
+
+					font.advances.insert(c, get("xadvance"));
+					if c == ' ' {
+						space_advance = Some(get("xadvance"));
+						continue;
+					}
+					font.text_hash.insert(c, Rect::new(get("x"), get("y"), get("width"), get("height")));
+					font.offsets.insert(c, vec2(get("xoffset"), get("yoffset")));
+				}
+
+				"kerning" => {
+					if let (Some(first), Some(second)) = (char::from_u32(get("first") as u32), char::from_u32(get("second") as u32)) {
+						font.kerning.insert((first, second), get("amount"));
+					}
+				}
+
+				_ => {}
+			}
+		}
+
+		if let Some(advance) = space_advance {
+			font.spacing.x = advance;
+		}
+		// a space only moves the pen, without a rect of its own it would be drawn as the default glyph
+		font.text_hash.insert(' ', Rect::new(0.0, 0.0, 0.0, 0.0));
+		if let Some(rect) = font.text_hash.get(&'?') {
+			font.def_rect = *rect;
+		}
+
+		font
+	}
+
+	// adds the advances and fallbacks of a description on top of the font. the rows, glyphs,
+	// spacing and default rect are about the description's own atlas, so they are only used with atlas_glyphs
+	pub fn apply_description(&mut self, parser: &StringParser, atlas_glyphs: bool) {
+		if atlas_glyphs {
+			if parser.get_string("spacing").is_some() {
+				self.spacing = parse_vec2(&parser.get_as_string_literal_or_def("spacing", ""));
+			}
+			if parser.get_string("defaultRect").is_some() {
+				self.def_rect = parse_rect(&parser.get_as_string_literal_or_def("defaultRect", ""));
+			}
+			self.add_atlas_glyphs(parser);
+		}
+
+		for advance in parser.get_all_strings("advance") {
+			if let Some((width, characters)) = advance.split_once('|') {
+				let width = parse_float(width);
+				for c in characters.trim().chars() {
+					self.advances.insert(c, width);
+				}
+			}
+		}
+
+		for fallback in parser.get_all_strings("fallback") {
+			if let Some((from, to)) = fallback.split_once('|') {
+				let from: Vec<char> = from.trim().chars().collect();
+				let to: Vec<char> = to.trim().chars().collect();
+				// either maps character by character or sends them all to one replacement
+				for i in 0..from.len() {
+					if let Some(replacement) = if to.len() == from.len() {to.get(i)} else {to.first()} {
+						self.fallbacks.insert(from[i], *replacement);
+					}
+				}
+			}
 		}
+	}
+
+	fn add_atlas_glyphs(&mut self, parser: &StringParser) {
+		let glyph_size = parse_vec2(&parser.get_as_string_literal_or_def("glyphSize", "8, 8"));
+		for row in parser.get_all_strings("row") {
+			if let Some((pos, characters)) = row.split_once('|') {
+				format_characters(&mut self.text_hash, characters.trim(), parse_vec2(pos), glyph_size);
+			}
+		}
+		// spaces in a row only skip a cell
+		self.text_hash.remove(&' ');
+
+		for glyph in parser.get_all_strings("glyph") {
+			if let Some((c, rect)) = glyph.split_once('|') {
+				if let Some(c) = parse_glyph_char(c) {
+					self.text_hash.insert(c, parse_rect(rect));
+				}
+			}
+		}
+	}
 
-		otp
+	// follows the fallback chain until it reaches a character the font can draw
+	fn resolve_char(&self, c: char) -> Option<char> {
+		let mut current = c;
+		for _ in 0..MAX_FALLBACK_DEPTH {
+			if self.text_hash.contains_key(&current) {
+				return Some(current);
+			}
+			current = *self.fallbacks.get(&current)?;
+		}
+		None
 	}
 
-	pub fn output_para(&self, text: &str) -> Vec<Vec<Rect>> {
-		let iter = text.split("\n");
-		let mut res = Vec::with_capacity(iter.clone().count());
+	pub fn get_rect(&self, c: char) -> Rect {
+		match self.resolve_char(c).and_then(|rc| self.text_hash.get(&rc)) {
+			Some(v) => *v,
+			None => self.def_rect
+		}
+	}
 
-		for s in iter {
-			res.push(self.output_text(s));
+	pub fn get_advance(&self, c: char) -> f32 {
+		if let Some(advance) = self.advances.get(&c) {
+			return *advance;
+		}
+		match self.resolve_char(c).and_then(|rc| self.advances.get(&rc)) {
+			Some(advance) => *advance,
+			None => self.spacing.x
 		}
+	}
+
+	pub fn get_line_height(&self) -> f32 {
+		self.spacing.y
+	}
+
+	pub fn get_offset(&self, c: char) -> Vec2 {
+		match self.resolve_char(c).and_then(|rc| self.offsets.get(&rc)) {
+			Some(offset) => *offset,
+			None => Vec2::ZERO
+		}
+	}
+
+	pub fn get_kerning(&self, first: char, second: char) -> f32 {
+		match (self.resolve_char(first), self.resolve_char(second)) {
+			(Some(a), Some(b)) => self.kerning.get(&(a, b)).copied().unwrap_or(0.0),
+			_ => 0.0
+		}
+	}
 
+	pub fn output_text(&self, text: &str) -> Vec<RGlyph> {
+		let styled: Vec<(char, TextStyle)> = parse_markup(text).into_iter().filter(|(c, _)| *c != '\n').collect();
+		self.make_glyphs(&styled)
+	}
+
+	pub fn output_para(&self, text: &str) -> Vec<Vec<RGlyph>> {
+		self.output_para_wrapped(text, None)
+	}
+
+	// max_width is in font units, so it has to be divided by the font size it will be drawn at
+	pub fn output_para_wrapped(&self, text: &str, max_width: Option<f32>) -> Vec<Vec<RGlyph>> {
+		let styled = parse_markup(text);
+		let mut res = Vec::new();
+
+		for line in styled.split(|(c, _)| *c == '\n') {
+			match max_width {
+				Some(width) if width > 0.0 => self.wrap_line(line, width, &mut res),
+				_ => res.push(self.make_glyphs(line)),
+			}
+		}
+
+		res
+	}
+
+	fn make_glyph(&self, c: char, next: Option<char>, style: TextStyle) -> RGlyph {
+		RGlyph {
+			rect: self.get_rect(c),
+			advance: self.get_advance(c) + next.map_or(0.0, |n| self.get_kerning(c, n)),
+			offset: self.get_offset(c),
+			style,
+		}
+	}
+
+	fn make_glyphs(&self, line: &[(char, TextStyle)]) -> Vec<RGlyph> {
+		let mut res = Vec::with_capacity(line.len());
+		for i in 0..line.len() {
+			let (c, style) = line[i];
+			res.push(self.make_glyph(c, line.get(i + 1).map(|(n, _)| *n), style));
+		}
 		res
 	}
 
-	pub fn draw_derived_text(&self, pos:Vec2, color: Color, font_size: f32, text: &[Rect]) {
+	fn wrap_line(&self, line: &[(char, TextStyle)], max_width: f32, res: &mut Vec<Vec<RGlyph>>) {
+		let space = self.make_glyph(' ', None, TextStyle::PLAIN);
+		let mut current: Vec<RGlyph> = Vec::new();
+		let mut current_width = 0.0;
+
+		for word in line.split(|(c, _)| *c == ' ') {
+			if word.is_empty() {continue;}
+
+			// words longer than a line get split wherever they run out of room
+			let mut chunk: Vec<RGlyph> = Vec::new();
+			let mut chunk_width = 0.0;
+			let mut chunks = Vec::new();
+			for glyph in self.make_glyphs(word) {
+				if !chunk.is_empty() && chunk_width + glyph.advance > max_width {
+					chunks.push((std::mem::take(&mut chunk), chunk_width));
+					chunk_width = 0.0;
+				}
+				chunk.push(glyph);
+				chunk_width += glyph.advance;
+			}
+			chunks.push((chunk, chunk_width));
+
+			for (mut chunk, chunk_width) in chunks {
+				if !current.is_empty() && current_width + space.advance + chunk_width > max_width {
+					res.push(std::mem::take(&mut current));
+					current_width = 0.0;
+				}
+				if !current.is_empty() {
+					current.push(space);
+					current_width += space.advance;
+				}
+				current.append(&mut chunk);
+				current_width += chunk_width;
+			}
+		}
+
+		res.push(current);
+	}
+
+	pub fn draw_derived_text(&self, pos:Vec2, color: Color, font_size: f32, text: &[RGlyph], time: f32) {
 		let mut delta = Vec2::ZERO;
 		for i in 0..text.len() {
-			let rect = &text[i];
-			
-			draw_texture_ex(&self.texture, pos.x + delta.x, pos.y + delta.y, color, DrawTextureParams{
+			let glyph = &text[i];
+			let rect = &glyph.rect;
+
+			let mut glyph_color = glyph.style.color.unwrap_or(color);
+			glyph_color.a *= color.a;
+
+			let motion = match glyph.style.effect {
+				TextEffect::Still => Vec2::ZERO,
+				TextEffect::Wave => vec2(0.0, f32::sin(time * WAVE_SPEED + i as f32 * WAVE_STEP) * WAVE_HEIGHT),
+				TextEffect::Shake => {
+					let seed = time * SHAKE_SPEED + i as f32 * 12.9898;
+					vec2(f32::sin(seed * 1.7).round(), f32::cos(seed * 2.3).round()) * SHAKE_AMOUNT
+				}
+			} * font_size + glyph.offset * font_size;
+
+			draw_texture_ex(&self.texture, pos.x + delta.x + motion.x, pos.y + delta.y + motion.y, glyph_color, DrawTextureParams{
 				dest_size: Some(rect.size() * font_size),
 				source: Some(*rect),
 				..DrawTextureParams::default()
 			});
 
-			delta.x += font_size * self.spacing.x;
+			delta.x += font_size * glyph.advance;
 		}
 	}
 
-	pub fn calc_text_length(&self, font_size: f32, text:&[Rect]) -> f32 {
-		let len = (text.len() as f32) * self.spacing.x;
+	pub fn calc_text_length(&self, font_size: f32, text:&[RGlyph]) -> f32 {
+		let len: f32 = text.iter().map(|g| g.advance).sum();
 
 		len * font_size
 	}
 
-	pub fn draw_derived_para(&self, pos:Vec2, color: Color, font_size: f32, para: &Vec<Vec<Rect>>) {
+	// pos is the left edge, center or right edge of the paragraph depending on align
+	pub fn draw_derived_para(&self, pos:Vec2, color: Color, font_size: f32, para: &[Vec<RGlyph>], align: TextAlign, time: f32) {
 		for i in 0..para.len() {
-			self.draw_derived_text(pos + vec2(0.0, font_size * self.spacing.y * (i as f32)), color, font_size, para[i].as_slice());
+			let line = para[i].as_slice();
+			let x_offset = match align {
+				TextAlign::Left => 0.0,
+				TextAlign::Center => -self.calc_text_length(font_size, line) * 0.5,
+				TextAlign::Right => -self.calc_text_length(font_size, line),
+			};
+			self.draw_derived_text(pos + vec2(x_offset, font_size * self.spacing.y * (i as f32)), color, font_size, line, time);
+		}
+	}
+}
+
+// reads text like "{red}DANGER{/}" into characters tagged with their style.
+// "{/}" goes back to the previous style and "{{" is a literal brace
+pub fn parse_markup(text: &str) -> Vec<(char, TextStyle)> {
+	let mut res = Vec::with_capacity(text.len());
+	let mut styles = vec![TextStyle::PLAIN];
+	let mut chars = text.chars().peekable();
+
+	while let Some(c) = chars.next() {
+		if c != '{' {
+			res.push((c, *styles.last().unwrap()));
+			continue;
+		}
+
+		if chars.peek() == Some(&'{') {
+			chars.next();
+			res.push(('{', *styles.last().unwrap()));
+			continue;
+		}
+
+		let mut tag = String::new();
+		let mut closed = false;
+		for tc in chars.by_ref() {
+			if tc == '}' {
+				closed = true;
+				break;
+			}
+			tag.push(tc);
+		}
+
+		let current = *styles.last().unwrap();
+		match (closed, apply_tag(current, &tag)) {
+			(true, Some(TagResult::Push(style))) => styles.push(style),
+			(true, Some(TagResult::Pop)) => {
+				if styles.len() > 1 {styles.pop();}
+			}
+			// unknown tags are drawn as they are so mistakes are easy to spot
+			_ => {
+				res.push(('{', current));
+				for tc in tag.chars() {
+					res.push((tc, current));
+				}
+				if closed {res.push(('}', current));}
+			}
+		}
+	}
+
+	res
+}
+
+enum TagResult {
+	Push(TextStyle),
+	Pop,
+}
+
+fn apply_tag(current: TextStyle, tag: &str) -> Option<TagResult> {
+	let tag = tag.trim().to_lowercase();
+	if tag == "/" {
+		return Some(TagResult::Pop);
+	}
+
+	let mut style = current;
+	match tag.as_str() {
+		"wave" => style.effect = TextEffect::Wave,
+		"shake" => style.effect = TextEffect::Shake,
+		"still" => style.effect = TextEffect::Still,
+		_ => style.color = Some(color_from_name(&tag)?),
+	}
+
+	Some(TagResult::Push(style))
+}
+
+pub fn color_from_name(name: &str) -> Option<Color> {
+	Some(match name {
+		"red" | "danger" => DANGER_COLOR,
+		"green" | "fly" => FB_COLOR,
+		"blue" | "player" => PLAYER_COLOR,
+		"gold" | "exit" => EXIT_COLOR,
+		"teal" | "breakable" => BREAKABLE_COLOR,
+		"white" => WHITE,
+		"gray" | "grey" => GRAY,
+		"yellow" => YELLOW,
+		_ => {
+			let hex = name.strip_prefix('#')?;
+			if hex.len() != 6 {return None;}
+			let v = u32::from_str_radix(hex, 16).ok()?.to_be_bytes();
+			Color::from_rgba(v[1], v[2], v[3], 255)
+		}
+	})
+}
+
+// loads the font described by Font.par. if it names a bmfont file that is used for the
+// glyphs, and the rest of the description is added on top of it
+pub async fn load_described_font(desc: &str, folder: &str, atlas: Texture2D) -> RFont {
+	let parser = StringParser::new(desc);
+	if let Some(fnt_name) = parser.get_string("bmfont") {
+		let fnt_path = format!("{}/{}", folder, fnt_name.trim());
+		match load_bmfont(&fnt_path).await {
+			Ok(mut font) => {
+				font.apply_description(&parser, false);
+				return font;
+			}
+			Err(e) => println!("Could not load {}: {}", fnt_path, e),
+		}
+	}
+
+	RFont::from_description(atlas, desc)
+}
+
+pub async fn load_bmfont(fnt_path: &str) -> Result<RFont, String> {
+	let fnt = vfs::load_string(fnt_path).await.map_err(|e| e.to_string())?;
+	let page = bmfont_page_file(&fnt).ok_or("no page in font file")?;
+
+	// page files are relative to the .fnt file
+	let folder = match fnt_path.rsplit_once('/') {
+		Some((folder, _)) => format!("{}/", folder),
+		None => String::new(),
+	};
+	let texture = vfs::load_texture(&format!("{}{}", folder, page)).await.map_err(|e| e.to_string())?;
+	texture.set_filter(FilterMode::Nearest);
+
+	Ok(RFont::from_bmfont(texture, &fnt))
+}
+
+pub fn bmfont_page_file(fnt: &str) -> Option<String> {
+	for line in fnt.lines() {
+		let (tag, values) = parse_bmfont_line(line);
+		if tag == "page" && values.get("id").is_none_or(|id| parse_int(id) == 0) {
+			return values.get("file").cloned();
+		}
+	}
+	None
+}
+
+// splits a line like `char id=65 x=0 y=0` into its tag and key value pairs
+fn parse_bmfont_line(line: &str) -> (&str, HashMap<&str, String>) {
+	let line = line.trim();
+	let (tag, mut rest) = line.split_once(' ').unwrap_or((line, ""));
+	let mut values = HashMap::new();
+
+	loop {
+		rest = rest.trim_start();
+		let (key, after_key) = match rest.split_once('=') {
+			Some(kv) => kv,
+			None => break,
+		};
+
+		let (value, after_value) = match after_key.strip_prefix('"') {
+			Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+			None => after_key.split_once(' ').unwrap_or((after_key, "")),
+		};
+
+		values.insert(key.trim(), value.to_string());
+		rest = after_value;
+	}
+
+	(tag, values)
+}
+
+fn parse_vec2(s: &str) -> Vec2 {
+	let v = parse_float_list(s);
+	vec2(v.first().copied().unwrap_or(0.0), v.get(1).copied().unwrap_or(0.0))
+}
+
+fn parse_rect(s: &str) -> Rect {
+	let v = parse_float_list(s);
+	let get = |i: usize| v.get(i).copied().unwrap_or(0.0);
+	Rect::new(get(0), get(1), get(2), get(3))
+}
+
+// a glyph is either the character itself or a code point written like U+003B
+fn parse_glyph_char(s: &str) -> Option<char> {
+	let s = s.trim();
+	match s.strip_prefix("U+") {
+		Some(hex) => char::from_u32(u32::from_str_radix(hex, 16).ok()?),
+		None => {
+			let mut chars = s.chars();
+			let c = chars.next()?;
+			if chars.next().is_some() {None} else {Some(c)}
 		}
 	}
 }
@@ -77,6 +558,7 @@ pub fn format_characters(hash_map:&mut HashMap<char,Rect>, characters: &str, off
 	}
 }
 
+#[allow(dead_code)]
 pub fn format_alphabet (hash_map:&mut HashMap<char,Rect>, offset: Vec2, char_size: Vec2) {
 	let mut scan_pos = offset;
 	let alphabet = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
@@ -86,4 +568,63 @@ pub fn format_alphabet (hash_map:&mut HashMap<char,Rect>, offset: Vec2, char_siz
 		scan_pos.x += char_size.x;
 	}
 
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const FONT_DESCRIPTION: &str = include_str!("../assets/Images/Font.par");
+
+	// the glyphs are only rects, so a texture that was never made is enough
+	fn no_texture() -> Texture2D {
+		Texture2D::from_miniquad_texture(miniquad::TextureId::from_raw_id(miniquad::RawId::OpenGl(0)))
+	}
+
+	#[test]
+	fn every_statement_in_the_font_description_is_read() {
+		let parser = StringParser::new(FONT_DESCRIPTION);
+		let fallbacks = FONT_DESCRIPTION.lines().filter(|line| line.starts_with("fallback")).count();
+		assert_eq!(parser.get_all_strings("fallback").len(), fallbacks);
+	}
+
+	#[test]
+	fn lowercase_falls_back_to_uppercase() {
+		let font = RFont::from_description(no_texture(), FONT_DESCRIPTION);
+		for (lower, upper) in "abcdefghijklmnopqrstuvwxyz".chars().zip("ABCDEFGHIJKLMNOPQRSTUVWXYZ".chars()) {
+			assert_eq!(font.resolve_char(lower), Some(upper), "{lower}");
+			assert_eq!(font.get_rect(lower), font.get_rect(upper), "{lower}");
+		}
+	}
+
+	#[test]
+	fn a_description_on_a_bmfont_keeps_its_metrics() {
+		let fnt = "common lineHeight=20 base=16 scaleW=64 scaleH=64 pages=1\nchar id=63 x=10 y=12 width=6 height=9 xoffset=0 yoffset=2 xadvance=7 page=0\nchar id=65 x=0 y=0 width=8 height=10 xoffset=0 yoffset=1 xadvance=9 page=0\n";
+		let mut font = RFont::from_bmfont(no_texture(), fnt);
+		font.apply_description(&StringParser::new(FONT_DESCRIPTION), false);
+		assert_eq!(font.get_line_height(), 20.0);
+		assert_eq!(font.get_rect('~'), Rect::new(10.0, 12.0, 6.0, 9.0));
+		// the fallbacks still come from the description
+		assert_eq!(font.resolve_char('a'), Some('A'));
+	}
+
+	#[test]
+	fn a_bmfont_space_draws_nothing() {
+		let fnt = "common lineHeight=20 base=16 scaleW=64 scaleH=64 pages=1\nchar id=32 x=30 y=30 width=4 height=4 xoffset=0 yoffset=0 xadvance=5 page=0\nchar id=63 x=10 y=12 width=6 height=9 xoffset=0 yoffset=2 xadvance=7 page=0\n";
+		let font = RFont::from_bmfont(no_texture(), fnt);
+		assert_eq!(font.get_rect(' ').size(), Vec2::ZERO);
+		assert_eq!(font.get_advance(' '), 5.0);
+
+		// the same without a space in the file
+		let font = RFont::from_bmfont(no_texture(), &fnt.replace("char id=32", "char id=33"));
+		assert_eq!(font.get_rect(' ').size(), Vec2::ZERO);
+	}
+
+	#[test]
+	fn accents_follow_the_chain() {
+		let font = RFont::from_description(no_texture(), FONT_DESCRIPTION);
+		assert_eq!(font.resolve_char('à'), Some('A'));
+		assert_eq!(font.resolve_char('É'), Some('E'));
+		assert_eq!(font.resolve_char('ñ'), Some('N'));
+	}
 }
\ No newline at end of file
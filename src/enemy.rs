@@ -3,6 +3,7 @@ use std::f32::consts;
 
 
 use crate::go_helpers::*;
+use crate::game_world::AssetManager;
 use crate::player;
 use macroquad::prelude::*;
 use crate::collision::*;
@@ -24,6 +25,9 @@ const TURRET_SPEED_BOOST: f32 = 1.75;
 const TURRET_BULLET_SPEED: f32 = 100.0;
 
 pub const ENEMY_DET_SIZE: f32 = 60.0;
+// the player is seen from further away in the light
+const DARK_DETECTION: f32 = 0.5;
+const LIT_DETECTION: f32 = 1.5;
 
 const BULLET_SIZE:f32 = 2.0;
 
@@ -33,9 +37,23 @@ const STALKER_SPEED_CHANGE: f32 = 2.4;
 const STALKER_TARGET_RADIUS: f32 = 64.0;
 const STALKER_ANGER_TIMER: f32 = 0.3;
 
+// what the enemies know about the player
+#[derive(Clone, Copy)]
+pub struct PlayerSense {
+	pub cbox: CollBox,
+	// 0 in the dark and 1 in bright light
+	pub visibility: f32,
+}
+
+pub fn detection_radius(visibility: f32) -> f32 {
+	ENEMY_DET_SIZE * (DARK_DETECTION + (LIT_DETECTION - DARK_DETECTION) * visibility)
+}
+
 pub struct Enemy {
 	pub cbox: CollBox,
 	pub enemy_type: EnemyType,
+	// set by the contacts of the last frame
+	pub in_fly_box: bool,
 }
 
 
@@ -44,12 +62,17 @@ impl Enemy {
 		Self {
 			cbox: collbox!(pos, Vec2::splat(4.0)),
 			enemy_type: entype,
+			in_fly_box: false,
 		}
 	}
 
 
-	pub fn update(&mut self, player_box: &CollBox, bullets: &mut Vec<Bullet>, in_fb: bool, trap_triggered: bool, dt: f32) {
-		let player_in_range = player_box.to_circle().check_intersection(&collcircle!(self.cbox.pos, ENEMY_DET_SIZE)) || trap_triggered;
+	pub fn update(&mut self, player: &PlayerSense, walls: &[MultiCollider<CollBox>], bullets: &mut Vec<Bullet>, matrix: &LayerMatrix, heard_noise: bool, dt: f32) {
+		let in_fb = self.in_fly_box;
+		let player_box = &player.cbox;
+		// has to be close and able to see the player through the walls
+		let player_in_range = (player_box.to_circle().check_intersection(&collcircle!(self.cbox.pos, detection_radius(player.visibility)))
+			&& line_of_sight(walls, self.cbox.pos, player_box.pos)) || heard_noise;
 		
 		let dir = (player_box.pos - self.cbox.pos).normalize_or_zero();
 		match self.enemy_type {
@@ -87,14 +110,20 @@ impl Enemy {
 						used_speed = STALKER_SLOW_SPEED
 					}
 					// anger ghost
-					if trap_triggered {anger_timer = STALKER_ANGER_TIMER;}
+					if heard_noise {anger_timer = STALKER_ANGER_TIMER;}
 					// chase player
 					if anger_timer > 0.0 {
 						used_speed = STALKER_FAST_SPEED;
 					}
 					// move ghost
 					velocity = move_toward_ex_2D(velocity, (player_box.pos- self.cbox.pos).normalize_or_zero() * used_speed, dt * STALKER_SPEED_CHANGE);
-					self.cbox.pos += velocity * dt;
+					self.enemy_type = EnemyType::StalkingGhost(velocity, anger_timer, agro);
+					let hit = if self.layers(matrix).reacts(LAYER_WALL) {MultiCollider::sweep_box_slice(walls, &self.cbox, velocity * dt)} else {None};
+					self.cbox.pos += velocity * dt * hit.map_or(1.0, |hit| hit.time);
+					if let Some(hit) = hit {
+						// slides along the wall from then on
+						velocity -= hit.normal * velocity.dot(hit.normal);
+					}
 				}
 				self.enemy_type = EnemyType::StalkingGhost(velocity, (anger_timer-dt).max(-0.1), agro);
 			}
@@ -103,28 +132,31 @@ impl Enemy {
 		}
 	}
 
-	pub fn draw(&self, texture: &Texture2D, player_pos: Vec2) {
+	pub fn draw(&self, assets: &AssetManager, player_pos: Vec2) {
 		//draw_rectangle(draw_loc.x, draw_loc.y, self.cbox.half_dim.x * 2.0, self.cbox.half_dim.y * 2.0, RED);
 		let flip_x =  self.cbox.pos.x > player_pos.x;
 		match self.enemy_type {
 			EnemyType::AngryGhosts(_, active)=> {
-				draw_centered_texture(&texture, self.cbox.pos, DANGER_COLOR, DrawTextureParams{
+				let (texture, source) = assets.sprite("angry_ghost");
+				draw_centered_texture(texture, self.cbox.pos, DANGER_COLOR, DrawTextureParams{
 					dest_size: Some(ENEMY_SIZE),
-					source: Some(Rect::new(0.0, 0.0, 16.0, 16.0)),
+					source: Some(source),
 					flip_x: flip_x,
 					..DrawTextureParams::default()
 				});
 				let eyes_offset = if active {(player_pos - self.cbox.pos).normalize_or_zero()} else {Vec2::ZERO} + vec2(0.0, -1.0);
-				draw_centered_texture(&texture, self.cbox.pos + eyes_offset, DANGER_COLOR, DrawTextureParams{
+				let (texture, source) = assets.sprite(if active {"ghost_eyes"} else {"ghost_eyes_closed"});
+				draw_centered_texture(texture, self.cbox.pos + eyes_offset, DANGER_COLOR, DrawTextureParams{
 					dest_size: Some(vec2(16.0, 8.0)),
-					source: Some(Rect::new(16.0, if !active {8.0} else {0.0}, 16.0, 8.0)),
+					source: Some(source),
 					..Default::default()
 				});
 			}
 
 			EnemyType::Turret(recov) => {
+				let (texture, source) = assets.sprite(if recov < TURRET_SHOOT_LENGTH {"turret_shooting"} else {"turret"});
 				draw_centered_texture(texture, self.cbox.pos, DANGER_COLOR, DrawTextureParams{
-					source: Some(Rect::new(if recov < TURRET_SHOOT_LENGTH {0.0} else {16.0}, 32.0, 16.0, 16.0)),
+					source: Some(source),
 					dest_size: Some(Vec2::splat(16.0)),
 					flip_x: flip_x,
 					..DrawTextureParams::default()
@@ -133,8 +165,9 @@ impl Enemy {
 
 			EnemyType::StalkingGhost(vel,anger_time , agro) => {
 				if anger_time <= 0.0 {
+					let (texture, source) = assets.sprite(if agro {"stalker_angry"} else {"stalker"});
 					draw_centered_texture(texture, self.cbox.pos, DANGER_COLOR, DrawTextureParams{
-						source: Some(Rect::new(if agro {0.0} else {16.0}, 48.0, 16.0, 32.0)),
+						source: Some(source),
 						dest_size: Some(Vec2::new(16.0, 32.0)),
 						flip_x: flip_x,
 						..Default::default()
@@ -147,8 +180,9 @@ impl Enemy {
 						let dist = player_pos - self.cbox.pos;
 						f32::atan2(dist.x, dist.y)
 					} + consts::PI/4.0;
+					let (texture, source) = assets.sprite("stalker_chasing");
 					draw_centered_texture(texture, self.cbox.pos, DANGER_COLOR, DrawTextureParams{
-						source: Some(Rect::new(0.0, 80.0, 32.0, 32.0)),
+						source: Some(source),
 						dest_size: Some(vec2(32.0, 32.0)),
 						rotation: angle,
 						..Default::default()
@@ -162,10 +196,14 @@ impl Enemy {
 		}
 	}
 
-	pub fn get_friendly_fire (&self) -> bool {
+	// only angry ghosts can be shot. stalkers drift through walls, but are too fast to stop
+	// in time while chasing so they run into them
+	pub fn layers(&self, matrix: &LayerMatrix) -> CollLayers {
+		let layers = CollLayers::new(LAYER_ENEMY, matrix);
 		match self.enemy_type {
-			EnemyType::AngryGhosts(_, _) => true,
-			_ => false,
+			EnemyType::AngryGhosts(_, _) => layers,
+			EnemyType::StalkingGhost(_, anger_timer, _) if anger_timer > 0.0 => layers.without(LAYER_BULLET).with(LAYER_WALL),
+			_ => layers.without(LAYER_BULLET),
 		}
 	}
 }
@@ -199,6 +237,8 @@ pub struct Bullet {
 	pub coll: CollCircle,
 	pub motion: Vec2,
 	pub life_time: Option<f32>,
+	// how far it moved in the last update
+	last_motion: Vec2,
 }
 
 impl Bullet {
@@ -206,13 +246,26 @@ impl Bullet {
 		Self {
 			coll: coll,
 			motion:motion,
-			life_time: life_time
+			life_time,
+			last_motion: Vec2::ZERO,
 		}
 	}
 
-	pub fn update(&mut self, dt:f32) {
-		self.coll.pos += self.motion * dt;
+	// moves up to the hit when there is one
+	pub fn update(&mut self, dt:f32, hit: Option<SweepHit>) {
+		self.last_motion = self.motion * dt * hit.map_or(1.0, |hit| hit.time);
+		self.coll.pos += self.last_motion;
+	}
+
+	// the box around where it went in the last update
+	pub fn swept_box(&self) -> CollBox {
+		swept_bounds(&self.coll.to_box(), -self.last_motion)
+	}
 
+	// if it touched cbox anywhere on the way to where it is now
+	pub fn hits_box(&self, cbox: &CollBox) -> bool {
+		check_circle_box_intersection(&self.coll, cbox)
+		|| collcircle!(self.coll.pos - self.last_motion, self.coll.radius).sweep_box(self.last_motion, cbox).is_some()
 	}
 
 	pub fn life_time_update (&mut self, dt:f32) -> bool {
@@ -229,4 +282,53 @@ impl Bullet {
 			false
 		}
 	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// the enemy part of the matrix GameWorld uses
+	fn matrix() -> LayerMatrix {
+		let mut matrix = LayerMatrix::new();
+		matrix.set(LAYER_ENEMY, LAYER_PLAYER | LAYER_BULLET | LAYER_TRIGGER, true);
+		matrix
+	}
+
+	#[test]
+	fn only_angry_ghosts_can_be_shot() {
+		let matrix = matrix();
+		let bullet = CollLayers::new(LAYER_BULLET, &matrix);
+		let mut angry_stalker = new_stalker(Vec2::ZERO);
+		angry_stalker.enemy_type = EnemyType::StalkingGhost(Vec2::ZERO, STALKER_ANGER_TIMER, true);
+
+		assert!(new_angry_ghost(Vec2::ZERO).layers(&matrix).contact(&bullet));
+		for enemy in [new_turret(Vec2::ZERO), new_stalker(Vec2::ZERO), angry_stalker] {
+			assert!(!enemy.layers(&matrix).contact(&bullet));
+		}
+	}
+
+	#[test]
+	fn only_angry_stalkers_are_stopped_by_walls() {
+		let matrix = matrix();
+		let mut stalker = new_stalker(Vec2::ZERO);
+		assert!(!stalker.layers(&matrix).reacts(LAYER_WALL));
+		stalker.enemy_type = EnemyType::StalkingGhost(Vec2::ZERO, STALKER_ANGER_TIMER, true);
+		assert!(stalker.layers(&matrix).reacts(LAYER_WALL));
+
+		assert!(!new_angry_ghost(Vec2::ZERO).layers(&matrix).reacts(LAYER_WALL));
+		assert!(!new_turret(Vec2::ZERO).layers(&matrix).reacts(LAYER_WALL));
+	}
+
+	#[test]
+	fn every_enemy_touches_the_player_and_triggers() {
+		let matrix = matrix();
+		let mut angry_stalker = new_stalker(Vec2::ZERO);
+		angry_stalker.enemy_type = EnemyType::StalkingGhost(Vec2::ZERO, STALKER_ANGER_TIMER, true);
+		for enemy in [new_angry_ghost(Vec2::ZERO), new_turret(Vec2::ZERO), new_stalker(Vec2::ZERO), angry_stalker] {
+			let layers = enemy.layers(&matrix);
+			assert_eq!(layers.layer, LAYER_ENEMY);
+			assert!(layers.reacts(LAYER_PLAYER) && layers.reacts(LAYER_TRIGGER));
+		}
+	}
 }
\ No newline at end of file
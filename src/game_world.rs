@@ -6,6 +6,7 @@ use std::default;
 
 use macroquad::prelude::*;
 use crate::collision::*;
+use crate::spatial_hash::*;
 use crate::enemy::*;
 use crate::go_helpers::*;
 
@@ -16,21 +17,42 @@ use crate::player;
 use crate::player::*;
 use crate::staticobj::*;
 use crate::wall_man::*;
+use crate::moving_platform::*;
+use crate::channels::*;
+use crate::noise::*;
 use crate::partical_system::{ParticalSystem, ParticalRenderer};
 
 use crate::options::*;
 use crate::custom_text::*;
 
 use crate::light_sources;
+use crate::localization::*;
+use crate::tiled::*;
+use crate::level_editor::*;
+use crate::vfs;
+use crate::pack_menu::PackMenu;
 
 const DEF_TILE:Vec2 = vec2(0.0, 3.0);
 
 const TILE_SIZE: f32 = 16.0;
 const TILE_VEC: Vec2 = Vec2::splat(TILE_SIZE);
+// how thick one way platforms are drawn, only their top collides
+const PLATFORM_THICKNESS: f32 = 4.0;
 
 const CAM_SPEED: f32 = 8.0;
 
+// cell size of the grids the entities are looked up in
+const ENTITY_GRID_SIZE: f32 = 32.0;
+// the layers that report contacts, walls only ever block movement
+const CONTACT_LAYERS: [u32; 6] = [LAYER_BREAKABLE, LAYER_GHOST_BLOCK, LAYER_PLAYER, LAYER_ENEMY, LAYER_BULLET, LAYER_TRIGGER];
+
 const TEXT_FADE_SPEED: f32 = 3.0;
+const NOTE_FONT_SIZE: f32 = 0.5;
+const NOTE_OFFSET: Vec2 = vec2(-64.0, -32.0);
+
+// the bar in the top right that shows how well the player can be seen
+const VISIBILITY_METER_SIZE: Vec2 = vec2(32.0, 4.0);
+const VISIBILITY_METER_MARGIN: f32 = 4.0;
 
 #[derive(Copy, Clone, PartialEq)]
 enum GameState {
@@ -39,8 +61,16 @@ enum GameState {
 	ForcePaused,
 }
 
+// something that can be in a contact, index is into the Vec of the things on its layer
+#[derive(Clone, Copy, PartialEq)]
+struct Body {
+	layer: u32,
+	index: usize,
+}
+
 pub struct GameWorld {
 	assets: AssetManager,
+	localization: Localization,
 
 	anim_timer: f32,
 
@@ -48,15 +78,29 @@ pub struct GameWorld {
 	lights: Vec<(Vec2, char)>,
 
 	triggers: Vec<Trigger>,
+	trigger_grid: SpatialHash<usize>,
 
 	breakable_walls: Vec<CollBox>,
+	breakable_grid: SpatialHash<usize>,
 	ghost_blocks: GhostBlocks,
+	moving_platforms: Vec<MovingPlatform>,
+	gates: Vec<Gate>,
+	channels: Channels,
 	
 	enemies: Vec<Enemy>,
+	enemy_grid: SpatialHash<usize>,
 
 	bullets: Vec<Bullet>,
+	bullet_grid: SpatialHash<usize>,
+
+	layer_matrix: LayerMatrix,
+	// from the contacts of the last frame
+	sprung_traps: Vec<usize>,
+	noises: Vec<Noise>,
 
 	pub player: Player,
+	// how well the enemies can see the player, from light_sources::visibility
+	player_visibility: f32,
 
 	pub cam_position: Vec2,
 
@@ -73,6 +117,15 @@ pub struct GameWorld {
 	pub level_index: i32,
 	pub load_new_level: bool,
 	level_blueprint: Vec<LevTile>,
+	level_path: String,
+	// None when the level comes from a Tiled map
+	level_layout: Option<String>,
+	// the channel lines of the level file, put back on the tiles whenever the layout is read again
+	level_channels: Vec<(u32, Vec2)>,
+	// the files the level was read from, the hot reloader watches these
+	level_sources: Vec<String>,
+	platform_paths: Vec<PlatformPath>,
+	ghost_grouping: GhostGrouping,
 	time_until_level_switch: Option<(f32, bool)>,
 
 
@@ -82,25 +135,39 @@ pub struct GameWorld {
 	wall_man: WallMan<Vec2>,
 
 	part_sys: ParticalSystem,
+
+	editor: Option<LevelEditor>,
 }
 
 impl GameWorld {
-	pub fn new(assets: AssetManager) -> Self {
+	pub fn new(assets: AssetManager, localization: Localization) -> Self {
 		let gw = Self {
 			// static objects
 			lights: Vec::new(),
 
 			triggers: Vec::new(),
+			trigger_grid: SpatialHash::new(ENTITY_GRID_SIZE),
 			breakable_walls: Vec::new(),
+			breakable_grid: SpatialHash::new(ENTITY_GRID_SIZE),
 			ghost_blocks: GhostBlocks::new(Vec2::splat(8.0)),
+			moving_platforms: Vec::new(),
+			gates: Vec::new(),
+			channels: Channels::new(),
 
 			// things that kill you
 			enemies: Vec::new(),
+			enemy_grid: SpatialHash::new(ENTITY_GRID_SIZE),
 
 			bullets: Vec::new(),
+			bullet_grid: SpatialHash::new(ENTITY_GRID_SIZE),
+
+			layer_matrix: game_layer_matrix(),
+			sprung_traps: Vec::new(),
+			noises: Vec::new(),
 			
 			// single instance objects
 			player: Player::new(),
+			player_visibility: 0.0,
 			level_exit: Option::None,
 
 			note: Option::None,
@@ -115,20 +182,29 @@ impl GameWorld {
 			level_index: 0,
 			load_new_level: false,
 			level_blueprint: Vec::new(),
+			level_path: String::new(),
+			level_layout: None,
+			level_channels: Vec::new(),
+			level_sources: Vec::new(),
+			platform_paths: Vec::new(),
+			ghost_grouping: GhostGrouping::Together,
 
 
 			time_until_level_switch: None,
 
 
-			hud: HUD::new(&assets, ""),
+			hud: HUD::new(&assets, &localization, None, "", 0.0, TextAlign::Left),
 			game_state: GameState::Unpaused,
 			assets: assets,
+			localization,
 
 			anim_timer: 0.0,
 
 			wall_man: WallMan::new(generate_multi_col_grid(Vec2::ZERO, Vec2::splat(128.0), uvec2(5,3)), DEF_TILE),
 
 			part_sys: ParticalSystem::new(),
+
+			editor: None,
 		};
 		gw
 	}
@@ -145,6 +221,9 @@ impl GameWorld {
 		self.enemies.clear();
 		self.bullets.clear();
 		self.ghost_blocks.clear();
+		self.moving_platforms = self.platform_paths.iter().cloned().map(MovingPlatform::new).collect();
+		self.gates.clear();
+		self.channels.clear();
 		self.level_exit = None;
 		self.note = None;
 
@@ -161,6 +240,7 @@ impl GameWorld {
 			let ent_pos = bp_tile.pos;
 			//self.world_size.x = self.world_size.x.max(ent_pos.x);
 			//self.world_size.y = self.world_size.y.max(ent_pos.y);
+			let channel = bp_tile.get_prop("channel").map_or(0, |c| parse_int(c).max(0) as u32);
 			match bp_tile.ty {
 				'p' => 	self.player.cbox.pos = ent_pos, // player
 
@@ -174,9 +254,9 @@ impl GameWorld {
 
 				'l' => self.lights.push((ent_pos, 'c')),
 
-				'g' => self.ghost_blocks.add(ent_pos),
+				'g' => self.ghost_blocks.add(ent_pos, channel),
 
-				'^' => self.triggers.push(Trigger::new(collbox!(ent_pos, Vec2::splat(7.0)), TriggerType::Trap(0.0))),
+				'^' => self.triggers.push(Trigger::new(collbox!(ent_pos, Vec2::splat(7.0)), TriggerType::Trap(0.0, channel))),
 
 				'f' => self.triggers.push(Trigger::new(collbox!(ent_pos, Vec2::splat(46.0*0.5)), TriggerType::FlyBox)), // fly_boxes
 				
@@ -184,6 +264,25 @@ impl GameWorld {
 
 				'/' => self.breakable_walls.push(collbox!(ent_pos, TILE_VEC*0.5)),
 
+				// switches, plates sit on the floor of their tile
+				'_' => self.triggers.push(Trigger::new(collbox!(ent_pos + vec2(0.0, TILE_SIZE * 0.5 - 2.0), (7.0, 2.0)), TriggerType::PressurePlate(channel, false))),
+				'L' => self.triggers.push(Trigger::new(collbox!(ent_pos, Vec2::splat(6.0)), TriggerType::Lever(channel, false, false))),
+				'k' => self.triggers.push(Trigger::new(collbox!(ent_pos, Vec2::splat(5.0)), TriggerType::Key(channel, false))),
+
+				// gates, D is shut until its channel is on and d is open until then
+				'D' => self.gates.push(Gate::new(collbox!(ent_pos, TILE_VEC*0.5), channel, false)),
+				'd' => self.gates.push(Gate::new(collbox!(ent_pos, TILE_VEC*0.5), channel, true)),
+
+				'=' => self.wall_man.add_platform(collbox!(ent_pos - vec2(0.0, (TILE_SIZE - PLATFORM_THICKNESS) * 0.5), (TILE_SIZE * 0.5, PLATFORM_THICKNESS * 0.5))), // one way platform
+
+				// slopes, < and > are 45 degrees and {[ ]} are 22.5 degrees over two tiles
+				'<' => self.wall_man.add_slope(CollSlope::new(collbox!(ent_pos, TILE_VEC*0.5), 0.0, TILE_SIZE)),
+				'>' => self.wall_man.add_slope(CollSlope::new(collbox!(ent_pos, TILE_VEC*0.5), TILE_SIZE, 0.0)),
+				'{' => self.wall_man.add_slope(CollSlope::new(collbox!(ent_pos, TILE_VEC*0.5), 0.0, TILE_SIZE * 0.5)),
+				'[' => self.wall_man.add_slope(CollSlope::new(collbox!(ent_pos, TILE_VEC*0.5), TILE_SIZE * 0.5, TILE_SIZE)),
+				']' => self.wall_man.add_slope(CollSlope::new(collbox!(ent_pos, TILE_VEC*0.5), TILE_SIZE, TILE_SIZE * 0.5)),
+				'}' => self.wall_man.add_slope(CollSlope::new(collbox!(ent_pos, TILE_VEC*0.5), TILE_SIZE * 0.5, 0.0)),
+
 				'X' => self.level_exit = Some((collbox!(ent_pos + vec2(0.0, 1.0) * (TILE_SIZE / 2.0 - 4.0 ) as f32, (4.0, 4.0)), 0.0)), // exit
 
 				'N' => self.note = Some(ent_pos), // Notes
@@ -217,18 +316,29 @@ impl GameWorld {
 			}else {Ordering::Greater}
 		},);
 
+		// the grids are keyed by index, so they are filled after the sort
+		self.trigger_grid.clear();
+		self.trigger_grid.update_all(&self.triggers, |t| t.coll);
+		self.breakable_grid.clear();
+		self.breakable_grid.update_all(&self.breakable_walls, |br| *br);
+		self.enemy_grid.clear();
+		self.enemy_grid.update_all(&self.enemies, |e| e.cbox);
+		self.bullet_grid.clear();
+		self.sprung_traps.clear();
+		self.noises.clear();
+
+		// the ghost blocks keep the trap indices too
+		let traps: Vec<(usize, Vec2, u32)> = self.triggers.iter().enumerate().filter_map(|(i, trigger)| match trigger.trigger_type {
+			TriggerType::Trap(_, channel) => Some((i, trigger.coll.pos, channel)),
+			_ => None,
+		}).collect();
+		self.ghost_blocks.build_groups(&traps, self.ghost_grouping);
+
+		self.wall_man.build_colliders();
 		self.wall_man.cull(); // removes empty multi colliders
 
 		// assigns a sprite to each tile
-		self.wall_man.assign_tile_types(|exp, coll| {
-			//return vec2(0.0, 3.0);
-			match (coll.x, coll.y) {
-				(true, true) => (ivec2(1, 1) - exp).as_vec2(),
-				(true, false) => if exp.x == 0 {vec2(2.0, 3.0)} else {DEF_TILE},
-				(false, true) => if exp.y == 0 {vec2(1.0, 3.0)} else {DEF_TILE},
-				(_) => vec2(0.0, 3.0),
-			}
-		}, Vec2::splat(8.5), Vec2::splat(0.1), Some(self.world_size));
+		self.wall_man.autotile(self.assets.get_autotiler(self.tileset), TILE_SIZE, Some(self.world_size));
 	
 	for i in 0..self.lights.len() {
 		self.lights[i].1 = light_sources::assign_light_type(self.lights[i].0, &self.wall_man.wall_colliders);
@@ -259,24 +369,30 @@ impl GameWorld {
 			return;
 		}
 
-		let mut queue_reset = false;
+		// moving platforms go first so whatever was on them moves with them
+		let riding = self.moving_platforms.iter().position(|platform| self.player.standing_on(&platform.cbox));
+		let carried: Vec<Vec<usize>> = self.moving_platforms.iter().map(|platform| {
+			platform.carry_zone().map_or(Vec::new(), |zone| self.enemy_grid.query_keys(&zone))
+		}).collect();
+		for platform in self.moving_platforms.iter_mut() {
+			platform.update(dt * self.time_speed);
+		}
+		for (platform, enemies) in self.moving_platforms.iter().zip(carried) {
+			for e in enemies {
+				self.enemies[e].cbox.pos += platform.motion;
+			}
+		}
+		if self.player.move_with_platforms(&self.moving_platforms, riding, &self.wall_man, &self.breakable_walls, &self.ghost_blocks) {
+			kill_player!();
+		}
+		let solids: Vec<CollBox> = self.moving_platforms.iter().map(|platform| platform.cbox)
+			.chain(self.gates.iter().filter(|gate| gate.is_solid()).map(|gate| gate.cbox)).collect();
+
 		// player logic
 		self.player.update(dt * self.time_speed);
-		let mut player_in_trap = false;
-		self.player.physics_update_mc(&self.wall_man.wall_colliders.as_slice(), &self.breakable_walls, &self.ghost_blocks, dt * self.time_speed);
-		Trigger::coll_check(self.triggers.as_mut_slice(), &self.player.cbox.clone(), |trigger|{
-			match trigger.trigger_type {
-				TriggerType::FlyBox => self.player.set_to_flying(),
-				
-				TriggerType::Spikes => queue_reset = true,
-				
-				TriggerType::Trap(_) => player_in_trap = true,
-				_ => {}
-			}
-			true
-		});
+		self.player.physics_update_mc(&self.wall_man, &self.breakable_walls, &self.ghost_blocks, &solids, dt * self.time_speed);
 
-		if self.player.cbox.pos.y > self.world_size.y || query(self.enemies.as_slice(), |enemy| {enemy.cbox.check_intersection(&self.player.cbox)}){
+		if self.player.cbox.pos.y > self.world_size.y {
 			kill_player!();
 		}
 
@@ -290,87 +406,62 @@ impl GameWorld {
 		
 		
 
-		// enemy logic
-		let mut enemy_in_nm = false;
-		run_and_remove(&mut self.enemies, |enemy| {
-			let mut dead = false;
-			let mut in_fb = false;
-			// removes enemies that collide with triggers
-			Trigger::coll_check(&mut self.triggers, &enemy.cbox, |trigger|{
-				match trigger.trigger_type {
-					TriggerType::Spikes => {
-						dead = true;
-						create_enemy_death_particals(&mut self.part_sys, enemy.cbox.pos);
-					},
-					
-					TriggerType::FlyBox => in_fb = true,
-					TriggerType::Trap(_) => enemy_in_nm = true,
-					_ => {}
-				}
-				true
-			});
-			enemy.update(&self.player.cbox, &mut self.bullets, in_fb,player_in_trap, dt * self.time_speed);
-			
-			return dead;
-		});
-
-		// ghostblock stuff
-		if self.player.check_ghost_block_kill(&self.ghost_blocks) {
-			kill_player!();
-			//println!("partical count: {}", self.part_sys.get_count());
+		// enemy logic, they only go after noises they can hear and see further in the light
+		self.player_visibility = light_sources::visibility(&self.lights, &self.wall_man.wall_colliders, self.player.cbox.pos);
+		let player_sense = PlayerSense {cbox: self.player.cbox, visibility: self.player_visibility};
+		for enemy in self.enemies.iter_mut() {
+			let heard_noise = self.noises.iter().any(|noise| noise.is_new() && noise.heard_at(&self.wall_man.wall_colliders, enemy.cbox.pos));
+			enemy.update(&player_sense, &self.wall_man.wall_colliders, &mut self.bullets, &self.layer_matrix, heard_noise, dt * self.time_speed);
 		}
-		self.ghost_blocks.update(player_in_trap || enemy_in_nm, dt);
+		self.enemy_grid.update_all(&self.enemies, |e| e.cbox);
+		self.noises.retain_mut(|noise| noise.update(dt * self.time_speed));
 
 		// bullet logic
-		run_and_remove(&mut self.bullets, |bullet|{
-			bullet.update(dt);
+		let bullet_layers = CollLayers::new(LAYER_BULLET, &self.layer_matrix);
+		let hits_ghost_blocks = bullet_layers.reacts(LAYER_GHOST_BLOCK);
+		run_and_swap_remove(&mut self.bullets, &mut self.bullet_grid, |bullet|{
+			// stops at the first wall in the way so fast bullets can't go through thin walls
+			let motion = bullet.motion * dt;
+			let mut hit = None;
+			if bullet_layers.reacts(LAYER_WALL) {
+				hit = MultiCollider::sweep_circle_slice(&self.wall_man.wall_colliders, &bullet.coll, motion);
+				hit = SweepHit::first(hit, bullet.coll.sweep_box_slice(motion, &solids));
+			}
+			if hits_ghost_blocks {
+				hit = SweepHit::first(hit, self.ghost_blocks.sweep_circle(&bullet.coll, motion));
+			}
+			bullet.update(dt, hit);
 			// check for wall collisions
-			if query(&self.wall_man.wall_colliders.as_slice(), |mc| {
+			if hit.is_some() || bullet_layers.reacts(LAYER_WALL) && (self.wall_man.slopes.iter().any(|slope| slope.contains_point(bullet.coll.pos)) || query(self.wall_man.wall_colliders.as_slice(), |mc| {
 				mc.check_intersection(&bullet.coll.to_box())
-			} || if self.ghost_blocks.get_block_effect() == GhostBlockEffect::Collide {
-				query(self.ghost_blocks.get_block_slice(), |gb| {check_circle_box_intersection(&bullet.coll, &gb)})
-			} else {false}) {
+			})) {
 				self.part_sys.create_partical(4, bullet.coll.pos, 1.0, bullet.coll.pos-bullet.motion * 0.1, 5.0, 1.0, 1, ParticalRenderer::Circle);
 				return true;
 			}
 			bullet.life_time_update(dt)
 		});
+		// the grid has the whole way the bullets went this frame, so nothing they passed is missed
+		self.bullet_grid.update_all(&self.bullets, |b| b.swept_box());
 
-		// enemy bullet interactions
-		for e in (0..self.enemies.len()).rev() {
-			// skips enemies that are immune to bullets
-			if !self.enemies[e].get_friendly_fire() {continue;}
-			let enemy = &self.enemies[e].cbox;
-			let mut remove_enemy = false;
-			for b in (0..self.bullets.len()).rev() {
-				let bullet = &self.bullets[b].coll;
-				if check_circle_box_intersection(&bullet, &enemy) {
-					remove_enemy = true;
-					self.bullets.remove(b);
-				}
-			}
-			if remove_enemy {
-				self.enemies.remove(e);
-			}
-		}
-		
-		if query(self.bullets.as_slice(), |bullet|
-			{check_circle_box_intersection(&bullet.coll, &self.player.cbox)}
-		) {
+		// everything else that touches
+		let contacts = self.find_contacts();
+		if self.resolve_contacts(&contacts) {
 			kill_player!();
 		}
-		
-		// handles breakable walls
-		run_and_remove(&mut self.breakable_walls, |br| {
-			let destroyed = 
-			query(&self.bullets, |bu| {check_circle_box_intersection(&bu.coll, &br)});
 
-			if destroyed {
-				self.part_sys.create_partical(15, br.pos, TILE_SIZE/2.0, br.pos - vec2(0.0, 16.0), TILE_SIZE, 1.0, 2, ParticalRenderer::Dust)
+		// the switches sent their events in resolve_contacts
+		for (channel, _) in self.channels.dispatch() {
+			for gate in self.gates.iter().filter(|gate| gate.channel == channel) {
+				self.part_sys.create_partical(6, gate.cbox.pos, TILE_SIZE/2.0, gate.cbox.pos - vec2(0.0, 16.0), TILE_SIZE, 1.0, 2, ParticalRenderer::Dust);
 			}
+		}
+		for gate in self.gates.iter_mut() {
+			let blocked = gate.cbox.check_intersection(&self.player.cbox);
+			gate.update(&self.channels, blocked, dt * self.time_speed);
+		}
 
-			destroyed
-		});
+		// ghostblock stuff
+		self.ghost_blocks.update(&self.sprung_traps, &self.channels, dt);
 
 
 		// door update
@@ -378,8 +469,8 @@ impl GameWorld {
 			let mut n_velocity_y = exit.1 + dt * PLAYER_GRAVITY;
 			let mut nbox = exit.0;
 			nbox.pos.y += n_velocity_y * dt;
-			if MultiCollider::collide_y_slice(&mut nbox, &self.wall_man.wall_colliders)
-			|| nbox.collide_y_slice(&self.breakable_walls){
+			if MultiCollider::collide_y_slice(&mut nbox, &self.wall_man.wall_colliders, n_velocity_y)
+			|| nbox.collide_y_slice(&self.breakable_walls, n_velocity_y){
 				n_velocity_y = 0.0;
 			}
 
@@ -402,29 +493,189 @@ impl GameWorld {
 		if let Some((time_til_switch, next_level)) = self.time_until_level_switch {
 			self.time_until_level_switch = Some((time_til_switch - dt, next_level));
 		}
+	}
 
-		if queue_reset {
-			kill_player!();
+	// every pair of things that touch and react to each other, only for the layer pairs in the matrix
+	fn find_contacts(&self) -> Vec<(Body, Body)> {
+		let mut contacts = Vec::new();
+		for (i, &a) in CONTACT_LAYERS.iter().enumerate() {
+			for &b in &CONTACT_LAYERS[i..] {
+				if !self.layer_matrix.reacts(a, b) {
+					continue;
+				}
+				// goes through the layer with less on it and looks the other one up
+				let swap = self.layer_len(b) < self.layer_len(a);
+				let (from, to) = if swap {(b, a)} else {(a, b)};
+				for index in 0..self.layer_len(from) {
+					let body = Body {layer: from, index};
+					let layers = self.body_layers(body);
+					if !layers.reacts(to) {
+						continue;
+					}
+					for other_index in self.layer_query(to, &self.body_box(body)) {
+						let other = Body {layer: to, index: other_index};
+						// a layer against itself would have every pair twice
+						if (from == to && other_index <= index) || !layers.contact(&self.body_layers(other)) || !self.bodies_touch(body, other) {
+							continue;
+						}
+						contacts.push(if swap {(other, body)} else {(body, other)});
+					}
+				}
+			}
+		}
+		contacts
+	}
+
+	// does whatever a contact does, the first body is always on the lower layer. returns true when the player dies
+	fn resolve_contacts(&mut self, contacts: &[(Body, Body)]) -> bool {
+		let mut player_dies = false;
+		let mut dead_enemies = Vec::new();
+		let mut dead_bullets = Vec::new();
+		let mut broken_walls = Vec::new();
+		// switches the player or an enemy is on
+		let mut touched = Vec::new();
+		let mut player_touched = Vec::new();
+
+		self.sprung_traps.clear();
+		for enemy in self.enemies.iter_mut() {
+			enemy.in_fly_box = false;
+		}
+
+		for &(a, b) in contacts {
+			match (a.layer, b.layer) {
+				(LAYER_PLAYER, LAYER_ENEMY) | (LAYER_PLAYER, LAYER_BULLET) => player_dies = true,
+
+				(LAYER_GHOST_BLOCK, LAYER_PLAYER) => player_dies |= self.ghost_blocks.block_effect(a.index) == GhostBlockEffect::Kill,
+
+				(LAYER_PLAYER, LAYER_TRIGGER) => {
+					match self.triggers[b.index].trigger_type {
+						TriggerType::FlyBox => self.player.set_to_flying(),
+						TriggerType::Spikes => player_dies = true,
+						TriggerType::Trap(..) => self.sprung_traps.push(b.index),
+						TriggerType::PressurePlate(..) | TriggerType::Lever(..) | TriggerType::Key(..) => {
+							touched.push(b.index);
+							player_touched.push(b.index);
+						}
+					}
+					// enemies are too quiet to be heard setting traps off
+					if self.triggers[b.index].activate() {
+						self.noises.push(Noise::new(self.triggers[b.index].coll.pos, TRAP_NOISE_RADIUS));
+					}
+				}
+
+				(LAYER_ENEMY, LAYER_TRIGGER) => {
+					let enemy = &mut self.enemies[a.index];
+					match self.triggers[b.index].trigger_type {
+						TriggerType::FlyBox => enemy.in_fly_box = true,
+						TriggerType::Spikes => if !dead_enemies.contains(&a.index) {
+							create_enemy_death_particals(&mut self.part_sys, enemy.cbox.pos);
+							dead_enemies.push(a.index);
+						},
+						TriggerType::Trap(..) => self.sprung_traps.push(b.index),
+						TriggerType::PressurePlate(..) => touched.push(b.index),
+						TriggerType::Lever(..) | TriggerType::Key(..) => {}
+					}
+					self.triggers[b.index].activate();
+				}
+
+				// a bullet only takes one enemy with it
+				(LAYER_ENEMY, LAYER_BULLET) if !dead_bullets.contains(&b.index) => {
+					dead_enemies.push(a.index);
+					dead_bullets.push(b.index);
+				},
+
+				// the bullet keeps going
+				(LAYER_BREAKABLE, LAYER_BULLET) if !broken_walls.contains(&a.index) => {
+					let br = self.breakable_walls[a.index];
+					self.part_sys.create_partical(15, br.pos, TILE_SIZE/2.0, br.pos - vec2(0.0, 16.0), TILE_SIZE, 1.0, 2, ParticalRenderer::Dust);
+					broken_walls.push(a.index);
+				},
+
+				// for bullets that were inside a block when it turned solid
+				(LAYER_GHOST_BLOCK, LAYER_BULLET) if self.ghost_blocks.block_effect(a.index) == GhostBlockEffect::Collide && !dead_bullets.contains(&b.index) => {
+					let bullet = &self.bullets[b.index];
+					self.part_sys.create_partical(4, bullet.coll.pos, 1.0, bullet.coll.pos-bullet.motion * 0.1, 5.0, 1.0, 1, ParticalRenderer::Circle);
+					dead_bullets.push(b.index);
+				},
+
+				_ => {}
+			}
+		}
+
+		swap_remove_all(&mut self.enemies, &mut self.enemy_grid, dead_enemies);
+		swap_remove_all(&mut self.bullets, &mut self.bullet_grid, dead_bullets);
+		swap_remove_all(&mut self.breakable_walls, &mut self.breakable_grid, broken_walls);
+		Trigger::update_switches(&mut self.triggers, &touched, &player_touched, &mut self.channels);
+		player_dies
+	}
+
+	fn layer_len(&self, layer: u32) -> usize {
+		match layer {
+			LAYER_BREAKABLE => self.breakable_walls.len(),
+			LAYER_GHOST_BLOCK => self.ghost_blocks.get_block_slice().len(),
+			LAYER_PLAYER => 1,
+			LAYER_ENEMY => self.enemies.len(),
+			LAYER_BULLET => self.bullets.len(),
+			LAYER_TRIGGER => self.triggers.len(),
+			_ => 0,
+		}
+	}
+
+	// the indices of everything on layer that overlaps cbox
+	fn layer_query(&self, layer: u32, cbox: &CollBox) -> Vec<usize> {
+		match layer {
+			LAYER_BREAKABLE => self.breakable_grid.query_keys(cbox),
+			LAYER_GHOST_BLOCK => self.ghost_blocks.query_near(cbox),
+			LAYER_PLAYER => if self.player.cbox.check_intersection(cbox) {vec![0]} else {Vec::new()},
+			LAYER_ENEMY => self.enemy_grid.query_keys(cbox),
+			LAYER_BULLET => self.bullet_grid.query_keys(cbox),
+			LAYER_TRIGGER => self.trigger_grid.query_keys(cbox),
+			_ => Vec::new(),
+		}
+	}
+
+	fn body_box(&self, body: Body) -> CollBox {
+		match body.layer {
+			LAYER_BREAKABLE => self.breakable_walls[body.index],
+			LAYER_GHOST_BLOCK => self.ghost_blocks.get_block_slice()[body.index],
+			LAYER_ENEMY => self.enemies[body.index].cbox,
+			LAYER_BULLET => self.bullets[body.index].swept_box(),
+			LAYER_TRIGGER => self.triggers[body.index].coll,
+			_ => self.player.cbox,
+		}
+	}
+
+	fn body_layers(&self, body: Body) -> CollLayers {
+		match body.layer {
+			LAYER_ENEMY => self.enemies[body.index].layers(&self.layer_matrix),
+			layer => CollLayers::new(layer, &self.layer_matrix),
+		}
+	}
+
+	// bullets are checked along the whole way they went, everything else by its box
+	fn bodies_touch(&self, a: Body, b: Body) -> bool {
+		match (a.layer, b.layer) {
+			(LAYER_BULLET, _) => self.bullets[a.index].hits_box(&self.body_box(b)),
+			(_, LAYER_BULLET) => self.bullets[b.index].hits_box(&self.body_box(a)),
+			_ => self.body_box(a).check_intersection(&self.body_box(b)),
 		}
 	}
 
 	
 
 	pub fn draw(&self) {
-		draw_background(&self.assets.world_images, Some(Rect::new(16.0 + 48.0 * self.tileset as f32, 16.0, 16.0, 16.0)), self.cam_position);
+		let (background, background_rect) = self.assets.tileset_frame("background", Vec2::ZERO, self.tileset);
+		draw_background(background, Some(background_rect), self.cam_position);
 
-		light_sources::draw_light_sources(&self.lights, &self.assets.world_images);
+		light_sources::draw_light_sources(&self.lights, &self.assets);
 
 		match &self.level_exit { // draws door
 			Some(exit) => {
 				let offset = TILE_SIZE / 2.0 - exit.0.half_dim.y;
-				draw_centered_texture(&self.assets.world_images, exit.0.pos - vec2(0.0, offset), EXIT_COLOR, DrawTextureParams{
+				let (texture, source) = self.assets.sprite(if !exit.0.check_intersection(&self.player.cbox) {"door_closed"} else {"door_open"});
+				draw_centered_texture(texture, exit.0.pos - vec2(0.0, offset), EXIT_COLOR, DrawTextureParams{
 					dest_size: Some(TILE_VEC),
-					source: if !exit.0.check_intersection(&self.player.cbox){
-						Some(Rect::new(0.0, 112.0, 16.0, 16.0))
-					} else {
-						Some(Rect::new(16.0, 112.0, 16.0, 16.0))
-					},
+					source: Some(source),
 					..DrawTextureParams::default()
 				});
 			}
@@ -434,8 +685,9 @@ impl GameWorld {
 
 		match &self.note {
 			Some(note) => {
-				draw_centered_texture(&self.assets.world_images, note.clone(), WHITE, DrawTextureParams{
-					source: Some(Rect::new(96.0, 96.0, 16.0, 16.0)),
+				let (texture, source) = self.assets.sprite("note");
+				draw_centered_texture(texture, note.clone(), WHITE, DrawTextureParams{
+					source: Some(source),
 					dest_size: Some(TILE_VEC),
 
 					..Default::default()
@@ -446,67 +698,107 @@ impl GameWorld {
 		}
 
 		for i in 0..self.triggers.len() {
-			self.triggers[i].draw(&self.assets.world_images);
+			self.triggers[i].draw(&self.assets);
 		}
 
-		self.player.draw(&self.assets.player_image);
+		for gate in &self.gates {
+			gate.draw(&self.assets);
+		}
+
+		self.player.draw(&self.assets);
 
 		self.wall_man.draw_walls(|wall_pos, wall_tile| {
 			let wall_size = TILE_VEC;
 			let draw_loc = wall_pos - wall_size * 0.5;
 
-			let wall_src_pos = (wall_tile.clone()+Vec2::new(3.0 * (self.tileset as f32), 0.0)) * Vec2::splat(16.0);
+			let (texture, source) = self.assets.tileset_frame("walls", wall_tile.clone(), self.tileset);
 			//println!("{}", &wall_tile);
-			draw_centered_texture(&self.assets.world_images, wall_pos, WHITE, DrawTextureParams{
-				source: Some(Rect::new(wall_src_pos.x, wall_src_pos.y, 16.0, 16.0)),
+			draw_centered_texture(texture, wall_pos, WHITE, DrawTextureParams{
+				source: Some(source),
 				dest_size: Some(wall_size),
 				..DrawTextureParams::default()
 			});
 		});
 
+		// slopes use the middle of a wall and platforms the top of a wall with nothing above or below it
+		let tiler = self.assets.get_autotiler(self.tileset);
+		let (slope_texture, slope_source) = self.assets.tileset_frame("walls", tiler.pick(u8::MAX), self.tileset);
+		for slope in &self.wall_man.slopes {
+			draw_slope(slope_texture, slope_source, slope);
+		}
+		let (platform_texture, mut platform_source) = self.assets.tileset_frame("walls", tiler.pick(0b0001_1000), self.tileset);
+		platform_source.h *= PLATFORM_THICKNESS / TILE_SIZE;
+		for platform in &self.wall_man.platforms {
+			draw_centered_texture(platform_texture, platform.pos, WHITE, DrawTextureParams{
+				source: Some(platform_source),
+				dest_size: Some(platform.half_dim * 2.0),
+				..DrawTextureParams::default()
+			});
+		}
+
+		// moving platforms are a row of the tops of walls
+		platform_source.h = platform_source.h / PLATFORM_THICKNESS * MOVING_PLATFORM_THICKNESS;
+		for platform in &self.moving_platforms {
+			let left = platform.cbox.pos.x - platform.cbox.half_dim.x + TILE_SIZE * 0.5;
+			for tile in 0..(platform.cbox.half_dim.x * 2.0 / TILE_SIZE).round() as i32 {
+				draw_centered_texture(platform_texture, vec2(left + tile as f32 * TILE_SIZE, platform.cbox.pos.y), WHITE, DrawTextureParams{
+					source: Some(platform_source),
+					dest_size: Some(vec2(TILE_SIZE, MOVING_PLATFORM_THICKNESS)),
+					..DrawTextureParams::default()
+				});
+			}
+		}
+
+		let (breakable_texture, breakable_source) = self.assets.sprite("breakable_wall");
 		for i in 0..self.breakable_walls.len() {
-			draw_centered_texture(&self.assets.world_images, self.breakable_walls[i].pos, BREAKABLE_COLOR, DrawTextureParams{
+			draw_centered_texture(breakable_texture, self.breakable_walls[i].pos, BREAKABLE_COLOR, DrawTextureParams{
 				dest_size: Some(Vec2::splat(16.0)),
-				source: Some(Rect::new(96.0, 112.0, 16.0, 16.0)),
+				source: Some(breakable_source),
 				..Default::default()
 			})
 		}
 
-		self.ghost_blocks.draw(&self.assets.world_images);
+		self.ghost_blocks.draw(&self.assets);
 
 		for i in 0..self.enemies.len() {
 			let enemy = &self.enemies[i];
-			enemy.draw(&self.assets.dangers, self.player.cbox.pos);
+			enemy.draw(&self.assets, self.player.cbox.pos);
 		}
 
+		let (bullet_texture, bullet_source) = self.assets.sprite("bullet");
 		for i in 0..self.bullets.len(){
 			let bullet = &self.bullets[i];
 			
-			draw_centered_texture(&self.assets.dangers, bullet.coll.pos, DANGER_COLOR, DrawTextureParams{
+			draw_centered_texture(bullet_texture, bullet.coll.pos, DANGER_COLOR, DrawTextureParams{
 				dest_size: Some(TILE_VEC),
-				source: Some(Rect::new(0.0, 16.0, 16.0, 16.0)),
+				source: Some(bullet_source),
 				pivot: Some(bullet.coll.pos),
 				rotation: f32::atan2(bullet.motion.y, bullet.motion.x),
 				..DrawTextureParams::default()
 			})
 		}
 
-		self.part_sys.draw(&self.assets.particals);
+		for noise in &self.noises {
+			noise.draw();
+		}
+
+		self.part_sys.draw(&self.assets);
 	}
 
 	pub fn draw_lights (&self) {
-		let light_tex = &self.assets.lights_tex;
-		draw_centered_texture(&light_tex, self.player.cbox.pos, PLAYER_GLOW, DrawTextureParams{
+		let (light_tex, round_light) = self.assets.sprite("light_round");
+		let (star_tex, star_light) = self.assets.sprite("light_star");
+		draw_centered_texture(light_tex, self.player.cbox.pos, PLAYER_GLOW, DrawTextureParams{
 			dest_size: Some(Vec2::splat(64.0+32.0)),
-			source: Some(Rect::new(0.0, 0.0, 32.0, 32.0)),
+			source: Some(round_light),
 
 			..Default::default()
 		});
 
 		if let Some(note) = self.note {
-			draw_centered_texture(&light_tex, note, GRAY, DrawTextureParams{
+			draw_centered_texture(star_tex, note, GRAY, DrawTextureParams{
 				dest_size: Some(Vec2::splat(64.0+16.0)),
-				source: Some(Rect::new(66.0, 0.0, 32.0, 32.0)),
+				source: Some(star_light),
 				rotation: self.anim_timer * f32::consts::PI / 4.0,
 				pivot: Some(note),
 				..Default::default()
@@ -514,16 +806,16 @@ impl GameWorld {
 		}
 
 		if let Some((coll, _)) = self.level_exit {
-			draw_centered_texture(&light_tex, coll.pos, GRAY, DrawTextureParams{
+			draw_centered_texture(star_tex, coll.pos, GRAY, DrawTextureParams{
 				dest_size: Some(Vec2::splat(128.0)),
-				source: Some(Rect::new(66.0, 0.0, 32.0, 32.0)),
+				source: Some(star_light),
 				rotation: self.anim_timer * f32::consts::PI / 8.0,
 				pivot: Some(coll.pos),
 				..Default::default()
 			});
 		}
 
-		light_sources::draw_lights(&self.lights, &self.assets.lights_tex);
+		light_sources::draw_lights(&self.lights, &self.assets);
 
 		for i in 0..self.triggers.len() {
 			let trigger = &self.triggers[i];
@@ -531,26 +823,34 @@ impl GameWorld {
 			match trigger.trigger_type {
 				TriggerType::Spikes => {
 					
-					draw_centered_texture(&light_tex, trigger.coll.pos, DANGER_LIGHT, DrawTextureParams{
+					draw_centered_texture(light_tex, trigger.coll.pos, DANGER_LIGHT, DrawTextureParams{
 						dest_size: Some(Vec2::splat(32.0+20.0)),
-						source: Some(Rect::new(0.0, 0.0, 32.0, 32.0)),
+						source: Some(round_light),
 						..Default::default()
 					})
 				}
 
 				TriggerType::FlyBox => {
-					draw_centered_texture(&light_tex, trigger.coll.pos, LIGHTGRAY, DrawTextureParams{
+					draw_centered_texture(light_tex, trigger.coll.pos, LIGHTGRAY, DrawTextureParams{
 						dest_size: Some(Vec2::splat(64.0+32.0)),
-						source: Some(Rect::new(0.0, 0.0, 32.0, 32.0)),
+						source: Some(round_light),
 						..Default::default()
 					})
 				}
 
-				TriggerType::Trap(time) => {
+				TriggerType::Trap(time, _) => {
 					if time <= 0.01 {continue;}
-					draw_centered_texture(&light_tex, trigger.coll.pos, WHITE, DrawTextureParams{
+					draw_centered_texture(light_tex, trigger.coll.pos, WHITE, DrawTextureParams{
 						dest_size: Some(Vec2::splat((time/TRAP_ANIM_TIME)*64.0)),
-						source: Some(Rect::new(0.0, 0.0, 32.0, 32.0)),
+						source: Some(round_light),
+						..Default::default()
+					})
+				}
+
+				TriggerType::Key(channel, false) => {
+					draw_centered_texture(light_tex, trigger.coll.pos, channel_color(channel), DrawTextureParams{
+						dest_size: Some(Vec2::splat(48.0)),
+						source: Some(round_light),
 						..Default::default()
 					})
 				}
@@ -560,9 +860,9 @@ impl GameWorld {
 
 		for i in 0..self.enemies.len() {
 			let enemy = &self.enemies[i];
-			draw_centered_texture(&light_tex, enemy.cbox.pos, DANGER_LIGHT, DrawTextureParams{
-				source: Some(Rect::new(0.0, 0.0, 32.0, 32.0)),
-				dest_size: Some(Vec2::splat(ENEMY_DET_SIZE*2.0)),
+			draw_centered_texture(light_tex, enemy.cbox.pos, DANGER_LIGHT, DrawTextureParams{
+				source: Some(round_light),
+				dest_size: Some(Vec2::splat(detection_radius(self.player_visibility)*2.0)),
 				..Default::default()
 			});
 		}
@@ -572,7 +872,92 @@ impl GameWorld {
 		let top_right = self.cam_position + vec2(CAM_WIDTH as f32, -(CAM_HEIGHT as f32));
 		let font = &self.assets.font;
 
-		self.hud.draw(&self.assets, self.cam_position);
+		self.hud.draw(&self.assets, self.cam_position, self.anim_timer);
+
+		let meter_pos = top_right + vec2(-VISIBILITY_METER_SIZE.x - VISIBILITY_METER_MARGIN, VISIBILITY_METER_MARGIN);
+		draw_rectangle(meter_pos.x, meter_pos.y, VISIBILITY_METER_SIZE.x, VISIBILITY_METER_SIZE.y, Color::new(0.0, 0.0, 0.0, 0.5));
+		draw_rectangle(meter_pos.x, meter_pos.y, VISIBILITY_METER_SIZE.x * self.player_visibility, VISIBILITY_METER_SIZE.y, lerp_color(WINDOW_LIGHT, EXIT_COLOR, self.player_visibility));
+		draw_rectangle_lines(meter_pos.x, meter_pos.y, VISIBILITY_METER_SIZE.x, VISIBILITY_METER_SIZE.y, 1.0, GRAY);
+
+		if let Some(editor) = &self.editor {
+			editor.draw(&self.assets.font, &self.localization, TILE_SIZE, self.cam_position, CAM_DIM, self.anim_timer);
+		}
+	}
+
+	pub fn draw_pack_menu(&self, menu: &PackMenu) {
+		menu.draw(&self.assets.font, &self.localization, self.cam_position, CAM_DIM, self.anim_timer);
+	}
+
+	pub fn is_editing(&self) -> bool {
+		self.editor.is_some()
+	}
+
+	pub fn is_typing(&self) -> bool {
+		self.editor.as_ref().is_some_and(|e| e.is_typing())
+	}
+
+	// switches between the editor and playing the level as it is in the editor
+	pub fn toggle_editor(&mut self) {
+		match self.editor.take() {
+			Some(editor) => {
+				self.apply_editor(&editor);
+				self.setup();
+			}
+
+			None => match &self.level_layout {
+				Some(layout) => {
+					let editor = LevelEditor::new(layout, self.hud.get_note_inline(), self.hud.has_note_key());
+					self.apply_editor(&editor);
+					let cam_position = self.cam_position;
+					self.setup();
+					self.cam_position = cam_position;
+					self.editor = Some(editor);
+				}
+
+				None => println!("Levels made in Tiled can't be edited in game"),
+			}
+		}
+	}
+
+	pub fn update_editor(&mut self, mouse_world: Vec2, dt: f32) {
+		let mut editor = match self.editor.take() {
+			Some(e) => e,
+			None => return,
+		};
+
+		self.anim_timer = (self.anim_timer + dt) % 4.0;
+		if editor.update(mouse_world, TILE_SIZE, &mut self.cam_position, dt) {
+			self.apply_editor(&editor);
+			let cam_position = self.cam_position;
+			self.setup();
+			self.cam_position = cam_position;
+		}
+
+		let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+		if ctrl && is_key_pressed(KeyCode::S) && !editor.is_typing() {
+			self.apply_editor(&editor);
+			match editor.save(&vfs::disk_path(&self.level_path)) {
+				Ok(()) => editor.set_message("Editor.Saved", &self.level_path),
+				Err(e) => editor.set_message("Editor.SaveFailed", &e.to_string()),
+			}
+		}
+
+		self.editor = Some(editor);
+	}
+
+	// puts the editor's layout and note into the level
+	fn apply_editor(&mut self, editor: &LevelEditor) {
+		let layout = editor.get_layout();
+		self.level_blueprint = read_level(&layout, TILE_SIZE);
+		attach_channels(&mut self.level_blueprint, &self.level_channels);
+		self.world_size = get_level_size(&layout).as_vec2() * TILE_SIZE;
+		self.world_size -= Vec2::ONE*TILE_SIZE;
+		self.level_layout = Some(layout);
+
+		if !self.hud.has_note_key() {
+			self.hud.set_note_inline(editor.get_note_text());
+			self.hud.relayout(&self.assets, &self.localization);
+		}
 	}
 
 	pub async fn handle_levels(&mut self, level_file_names: &Vec<String>) {
@@ -610,21 +995,60 @@ impl GameWorld {
 	}
 
 	pub async fn load_level(&mut self, level_path:&str) {
-		match load_string(level_path).await {
+		match vfs::load_string(level_path).await {
 			Result::Ok(level_file)=> {
+				self.editor = None;
+				self.level_path = level_path.to_string();
+				self.level_sources = vec![level_path.to_string()];
 				let parser = StringParser::new(level_file.as_str());
 				self.time_speed = parser.get_float_or_def("timeScale", 1.0);
 				
-				let mut level_string = parser.get_as_string_literal_or_def("layout", "P\n\n#");
-				level_string = level_string.replace("\n\n", "\n");
+				let level_string = read_layout(&parser);
 				//parser.print_names();
-				level_string.remove(0);
 				self.tileset = parser.get_int_or_def("tileset", 0).abs() as u32;
+				self.ghost_grouping = GhostGrouping::from_name(&parser.get_string_or_def("ghostGroups", String::from("together")));
+				self.platform_paths = parser.get_all_strings("movingPlatform").into_iter().filter_map(|value| {
+					PlatformPath::parse(value, TILE_SIZE).map_err(|e| println!("Error. Moving platform {} in {}: {}", value.trim(), level_path, e)).ok()
+				}).collect();
+
+				// noteKey looks the note up in the string tables, noteText is the old inline note
+				let note_key = parser.get_string("noteKey").map(|k| remove_whitespace(k));
+				self.hud = HUD::new(&self.assets, &self.localization, note_key,
+					&parser.get_as_string_literal_or_def("noteText", "").replace("\n\n", "\n"),
+					parser.get_float_or_def("noteWidth", 0.0),
+					TextAlign::from_name(&parser.get_string_or_def("noteAlign", String::from("left"))));
+
+				// levels made in Tiled keep their settings in the .par file and point at the map
+				match parser.get_string("tiledMap") {
+					Some(map_name) => {
+						let folder = level_path.rsplit_once('/').map_or("", |(f, _)| f);
+						let map_path = format!("{}/{}", folder, remove_whitespace(map_name));
+						let glyphs = parser.get_as_string_literal_or_def("tiledGlyphs", "").trim().to_string();
+						self.level_sources.push(map_path.clone());
+						match load_tiled_level(&map_path, &glyphs).await {
+							Ok(level) => {
+								self.level_blueprint = level.tiles;
+								self.world_size = level.size.as_vec2() * TILE_SIZE;
+								self.level_layout = None;
+							}
+							Err(e) => {
+								println!("Error. Could not load {}: {}", &map_path, e);
+								self.level_blueprint = read_level(&level_string, TILE_SIZE);
+								self.world_size = get_level_size(&level_string).as_vec2() * TILE_SIZE;
+								self.level_layout = None;
+							}
+						}
+					}
+
+					None => {
+						self.level_blueprint = read_level(&level_string, TILE_SIZE);
+						self.world_size = get_level_size(&level_string).as_vec2() * TILE_SIZE;
+						self.level_layout = Some(level_string);
+					}
+				}
 
-				self.hud = HUD::new(&self.assets, &parser.get_as_string_literal_or_def("noteText", "").to_uppercase().replace("\n\n", "\n"));
-
-				self.level_blueprint = read_level(&level_string, TILE_SIZE);
-				self.world_size = get_level_size(&level_string).as_vec2() * TILE_SIZE;
+				self.level_channels = read_channels(&parser, TILE_SIZE);
+				attach_channels(&mut self.level_blueprint, &self.level_channels);
 				self.world_size -= Vec2::ONE*TILE_SIZE;
 			}
 
@@ -638,8 +1062,31 @@ impl GameWorld {
 		self.cam_position = self.cam_position.clamp(CAM_DIM, self.world_size - CAM_DIM);
 	}
 
+	// reads the current level again and starts it over
+	pub async fn reload_level(&mut self) {
+		let level_path = self.level_path.clone();
+		self.load_level(&level_path).await;
+		self.setup();
+	}
+
+	pub fn get_level_sources(&self) -> &[String] {
+		&self.level_sources
+	}
+
 	pub fn set_assets(&mut self, assets:AssetManager) {
 		self.assets = assets;
+		// the font and the autotiling rules might have changed
+		self.hud.relayout(&self.assets, &self.localization);
+		self.wall_man.autotile(self.assets.get_autotiler(self.tileset), TILE_SIZE, Some(self.world_size));
+	}
+
+	pub fn set_localization(&mut self, localization: Localization) {
+		self.localization = localization;
+		self.hud.relayout(&self.assets, &self.localization);
+	}
+
+	pub fn get_language(&self) -> &str {
+		self.localization.get_language()
 	}
 
 	pub fn queue_level_load(&mut self, load_next_level: bool){
@@ -651,67 +1098,296 @@ impl GameWorld {
 	}
 }
 
+pub const ASSET_FOLDER: &str = "assets/Images";
+pub const MANIFEST_PATH: &str = "assets/Images/Assets.par";
+// used when the manifest is missing, the same file as it was when compiling
+const DEFAULT_MANIFEST: &str = include_str!("../assets/Images/Assets.par");
+const DEFAULT_AUTOTILE: &str = include_str!("../assets/Images/Autotile.par");
+
+// a named region of one of the textures
+#[derive(Clone)]
+pub struct Sprite {
+	pub texture: String,
+	pub rect: Rect,
+	// space between the frames of an animation or tile set
+	pub gap: f32,
+}
+
 pub struct AssetManager {
-	player_image: Texture2D,
-	dangers: Texture2D,
-	world_images: Texture2D,
-	particals: Texture2D,
-	lights_tex: Texture2D,
+	textures: HashMap<String, Texture2D>,
+	sprites: HashMap<String, Sprite>,
+	// drawn for textures and sprites that aren't in the manifest
+	placeholder: Texture2D,
+	tileset_stride: f32,
+	autotile: AutoTiler<Vec2>,
+	tileset_autotiles: HashMap<u32, AutoTiler<Vec2>>,
 	font: RFont
 }
 
 impl AssetManager {
-	pub async fn new() -> Self {
-		let player_image: Texture2D = load_texture("assets/Images/Player.png").await.unwrap();
-		player_image.set_filter(FilterMode::Nearest);
+	// debug builds put a placeholder in for textures that can't be loaded, release builds fail
+	pub async fn new() -> Result<Self, AssetError> {
+		let manifest = match vfs::load_string(MANIFEST_PATH).await {
+			Ok(manifest) => manifest,
+
+			Err(_) => {
+				println!("Could not load {}, using the built in manifest", MANIFEST_PATH);
+				DEFAULT_MANIFEST.to_string()
+			}
+		};
+		let parser = StringParser::new(&manifest);
+
+		let mut textures = HashMap::new();
+		let mut sprites = HashMap::new();
+		read_manifest(&parser, MANIFEST_PATH, &mut textures, &mut sprites).await?;
+		// region files written by the atlas packer
+		for include in parser.get_all_strings("include") {
+			let path = format!("{}/{}", ASSET_FOLDER, remove_whitespace(include));
+			let included = vfs::load_string(&path).await.map_err(|_| AssetError::Missing(path.clone()))?;
+			read_manifest(&StringParser::new(&included), &path, &mut textures, &mut sprites).await?;
+		}
+
+		// sprites are checked after all the textures are in, so the order in the file doesn't matter
+		for (sprite_name, sprite) in sprites.iter() {
+			if !textures.contains_key(&sprite.texture) {
+				return Err(AssetError::Invalid(MANIFEST_PATH.to_string(),
+					format!("sprite {} uses the unknown texture {}", sprite_name, sprite.texture)));
+			}
+		}
+
+		let autotile = match parser.get_string("autotile") {
+			Some(file) => load_autotiler(&format!("{}/{}", ASSET_FOLDER, remove_whitespace(file))).await?,
+			None => parse_autotiler(DEFAULT_AUTOTILE).map_err(|e| AssetError::Invalid(String::from("built in autotile rules"), e))?,
+		};
+		let mut tileset_autotiles = HashMap::new();
+		for name in parser.get_names() {
+			if let Some(tileset) = name.strip_prefix("autotile.") {
+				let file = remove_whitespace(parser.get_string(name).map_or("", |v| v.as_str()));
+				tileset_autotiles.insert(parse_int(tileset).max(0) as u32, load_autotiler(&format!("{}/{}", ASSET_FOLDER, file)).await?);
+			}
+		}
 
-		let world_images = load_texture("assets/Images/LevelImages.png").await.unwrap();
-		world_images.set_filter(FilterMode::Nearest);
+		let placeholder = placeholder_texture();
+		let font_texture = parser.get_string("fontTexture").map_or(String::from("font"), |id| remove_whitespace(id));
+		let font_image = textures.get(&font_texture).cloned().unwrap_or_else(|| placeholder.clone());
+		let font_desc = parser.get_string("fontDescription").map_or(String::from("Font.par"), |f| remove_whitespace(f));
+		let font = match vfs::load_string(&format!("{}/{}", ASSET_FOLDER, font_desc)).await {
+			Ok(desc) => load_described_font(&desc, ASSET_FOLDER, font_image).await,
 
-		let dangers = load_texture("assets/Images/Dangers.png").await.unwrap();
-		dangers.set_filter(FilterMode::Nearest);
+			// old layout, for when the description file is missing
+			Err(_) => {
+				let mut hash = HashMap::new();
 
-		let particals = load_texture("assets/Images/Particals.png").await.unwrap();
-		particals.set_filter(FilterMode::Nearest);
+				format_alphabet(&mut hash, Vec2::ZERO, Vec2::splat(8.0));
 
-		let lights = load_texture("assets/Images/Lights.png").await.unwrap();
-		lights.set_filter(FilterMode::Linear);
+				format_characters(&mut hash, "0123456789.,:!'", vec2(0.0, 8.0), Vec2::splat(8.0));
+				RFont::new(font_image, hash, Rect::new(0.0, 0.0, 1.0, 1.0), vec2(7.0, 9.0))
+			}
+		};
 		
-		let font_image = load_texture("assets/Images/Font.png").await.unwrap();
-		font_image.set_filter(FilterMode::Nearest);
-		let mut hash = HashMap::new();
+		Ok(Self {
+			textures,
+			sprites,
+			placeholder,
+			tileset_stride: parser.get_float_or_def("tilesetStride", 3.0),
+			autotile,
+			tileset_autotiles,
+			font,
+		})
+	}
 
-		format_alphabet(&mut hash, Vec2::ZERO, Vec2::splat(8.0));
+	pub fn texture(&self, id: &str) -> &Texture2D {
+		self.textures.get(id).unwrap_or(&self.placeholder)
+	}
 
-		format_characters(&mut hash, "0123456789.,:!'", vec2(0.0, 8.0), Vec2::splat(8.0));
-		
-		Self {
-			player_image: player_image,
-			world_images: world_images,
-			dangers: dangers,
+	pub fn sprite(&self, name: &str) -> (&Texture2D, Rect) {
+		self.sprite_frame(name, Vec2::ZERO)
+	}
 
-			particals: particals,
+	// frames packed by atlas_pack have a gap with their edges extruded into it
+	pub fn sprite_has_gap(&self, name: &str) -> bool {
+		self.sprites.get(name).is_some_and(|sprite| sprite.gap > 0.0)
+	}
 
-			lights_tex: lights,
+	// the region frame.x sprites to the right and frame.y sprites down from the named one
+	pub fn sprite_frame(&self, name: &str, frame: Vec2) -> (&Texture2D, Rect) {
+		match self.sprites.get(name) {
+			Some(sprite) => {
+				let rect = sprite.rect;
+				let stride = rect.size() + Vec2::splat(sprite.gap);
+				(self.texture(&sprite.texture), Rect::new(rect.x + frame.x * stride.x, rect.y + frame.y * stride.y, rect.w, rect.h))
+			}
 
-			font: RFont::new(font_image, hash, Rect::new(0.0, 0.0, 1.0, 1.0), vec2(7.0, 9.0)),
+			None => (&self.placeholder, Rect::new(0.0, 0.0, 16.0, 16.0)),
 		}
 	}
+
+	pub fn get_autotiler(&self, tileset: u32) -> &AutoTiler<Vec2> {
+		self.tileset_autotiles.get(&tileset).unwrap_or(&self.autotile)
+	}
+
+	// the same tile in another tile set
+	pub fn tileset_frame(&self, name: &str, tile: Vec2, tileset: u32) -> (&Texture2D, Rect) {
+		self.sprite_frame(name, tile + vec2(self.tileset_stride * tileset as f32, 0.0))
+	}
+}
+
+async fn load_autotiler(path: &str) -> Result<AutoTiler<Vec2>, AssetError> {
+	let src = vfs::load_string(path).await.map_err(|_| AssetError::Missing(path.to_string()))?;
+	parse_autotiler(&src).map_err(|e| AssetError::Invalid(path.to_string(), e))
+}
+
+fn parse_autotiler(src: &str) -> Result<AutoTiler<Vec2>, String> {
+	let parser = StringParser::new(src);
+	let default = parser.get_as_floats("default");
+	let mut tiler = AutoTiler::new(match default.as_slice() {
+		[x, y, ..] => vec2(*x, *y),
+		_ => DEF_TILE,
+	});
+	for rule in parser.get_all_strings("rule") {
+		let rule = remove_whitespace(rule);
+		let mut parts = rule.splitn(3, ',');
+		let (Some(x), Some(y), Some(pattern)) = (parts.next(), parts.next(), parts.next()) else {
+			return Err(format!("rule {} should be tile x, tile y, neighbours", rule));
+		};
+		tiler.add_rule(pattern, vec2(parse_float(x), parse_float(y)))?;
+	}
+	Ok(tiler)
+}
+
+async fn read_manifest(parser: &StringParser, path: &str, textures: &mut HashMap<String, Texture2D>, sprites: &mut HashMap<String, Sprite>) -> Result<(), AssetError> {
+	for name in parser.get_names() {
+		let value = remove_whitespace(parser.get_string(name).map_or("", |v| v.as_str()));
+		if let Some(id) = name.strip_prefix("texture.") {
+			let (file, filter) = value.split_once(',').unwrap_or((&value, "nearest"));
+			let filter = match filter.to_lowercase().as_str() {
+				"linear" => FilterMode::Linear,
+				_ => FilterMode::Nearest,
+			};
+			let texture = load_asset_texture(&format!("{}/{}", ASSET_FOLDER, file), filter).await?;
+			textures.insert(id.to_string(), texture);
+		} else if let Some(sprite_name) = name.strip_prefix("sprite.") {
+			let sprite = parse_sprite(&value).ok_or_else(|| AssetError::Invalid(path.to_string(),
+				format!("sprite {} should be texture, x, y, width, height and maybe gap", sprite_name)))?;
+			sprites.insert(sprite_name.to_string(), sprite);
+		}
+	}
+	Ok(())
+}
+
+fn parse_sprite(value: &str) -> Option<Sprite> {
+	let (texture, rect) = value.split_once(',')?;
+	let rect = parse_float_list(rect);
+	if rect.len() != 4 && rect.len() != 5 {
+		return None;
+	}
+	Some(Sprite {
+		texture: texture.to_string(),
+		rect: Rect::new(rect[0], rect[1], rect[2], rect[3]),
+		gap: rect.get(4).copied().unwrap_or(0.0),
+	})
+}
+
+#[derive(Debug)]
+pub enum AssetError {
+	Missing(String),
+	// the file is there but isn't an image that can be read
+	Invalid(String, String),
+}
+
+impl std::fmt::Display for AssetError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			AssetError::Missing(path) => write!(f, "Missing asset file {}", path),
+			AssetError::Invalid(path, reason) => write!(f, "Invalid asset file {}: {}", path, reason),
+		}
+	}
+}
+
+impl std::error::Error for AssetError {}
+
+const PLACEHOLDER_SIZE: u16 = 128;
+const PLACEHOLDER_CHECK: u16 = 8;
+
+async fn load_asset_texture(path: &str, filter: FilterMode) -> Result<Texture2D, AssetError> {
+	let texture = match vfs::load_texture(path).await {
+		Ok(texture) => texture,
+
+		Err(e) => {
+			let error = match e {
+				macroquad::Error::FileError { .. } => AssetError::Missing(path.to_string()),
+				e => AssetError::Invalid(path.to_string(), e.to_string()),
+			};
+			if !cfg!(debug_assertions) {
+				return Err(error);
+			}
+			println!("{}, using a placeholder", error);
+			placeholder_texture()
+		}
+	};
+	texture.set_filter(filter);
+	Ok(texture)
+}
+
+// magenta and black checkerboard that is hard to miss in game
+fn placeholder_texture() -> Texture2D {
+	let mut image = Image::gen_image_color(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, BLACK);
+	for y in 0..PLACEHOLDER_SIZE as u32 {
+		for x in 0..PLACEHOLDER_SIZE as u32 {
+			if (x / PLACEHOLDER_CHECK as u32 + y / PLACEHOLDER_CHECK as u32).is_multiple_of(2) {
+				image.set_pixel(x, y, MAGENTA);
+			}
+		}
+	}
+	Texture2D::from_image(&image)
 }
 
 pub struct HUD {
-	note_text: Vec<Vec<Rect>>,
+	note_text: Vec<Vec<RGlyph>>,
+	note_key: Option<String>,
+	note_inline: String,
+	note_width: f32,
+	note_align: TextAlign,
 
 	note_opacity: f32,
 }
 
 impl HUD {
-	pub fn new(assets: &AssetManager, note_text: &str) -> Self {
-		Self {
-			note_text: assets.font.output_para(note_text),
+	// note_width is in pixels, 0 turns word wrapping off. note_inline is only used without a key
+	pub fn new(assets: &AssetManager, localization: &Localization, note_key: Option<String>, note_inline: &str, note_width: f32, note_align: TextAlign) -> Self {
+		let mut hud = Self {
+			note_text: Vec::new(),
+			note_key,
+			note_inline: note_inline.to_string(),
+			note_width,
+			note_align,
 
 			note_opacity: 0.0
-		}
+		};
+		hud.relayout(assets, localization);
+		hud
+	}
+
+	pub fn has_note_key(&self) -> bool {
+		self.note_key.is_some()
+	}
+
+	pub fn get_note_inline(&self) -> &str {
+		&self.note_inline
+	}
+
+	pub fn set_note_inline(&mut self, note_inline: &str) {
+		self.note_inline = note_inline.to_string();
+	}
+
+	// redoes the note text, for when the language or font changes
+	pub fn relayout(&mut self, assets: &AssetManager, localization: &Localization) {
+		let text = match &self.note_key {
+			Some(key) => localization.get_or_key(key),
+			None => self.note_inline.clone(),
+		};
+		self.note_text = assets.font.output_para_wrapped(&text,
+			if self.note_width > 0.0 {Some(self.note_width / NOTE_FONT_SIZE)} else {None});
 	}
 
 	pub fn update (&mut self, player_reading: bool, dt: f32) {
@@ -720,20 +1396,78 @@ impl HUD {
 		).clamp(0.0, 1.0);
 	}
 
-	pub fn draw (&self, assets: &AssetManager, camera_pos: Vec2) {
-		let offset = vec2(-64.0, -32.0);
+	pub fn draw (&self, assets: &AssetManager, camera_pos: Vec2, time: f32) {
+		let offset = match self.note_align {
+			TextAlign::Left => NOTE_OFFSET,
+			TextAlign::Center => vec2(0.0, NOTE_OFFSET.y),
+			TextAlign::Right => vec2(-NOTE_OFFSET.x, NOTE_OFFSET.y),
+		};
 		let mut color = WHITE;
 		color.a = self.note_opacity;
-		assets.font.draw_derived_para(camera_pos + offset, color, 0.5, &self.note_text);
+		assets.font.draw_derived_para(camera_pos + offset, color, NOTE_FONT_SIZE, &self.note_text, self.note_align, time);
 	}
 }
 
 pub struct LevTile {
 	pub pos: Vec2,
 	pub ty: char,
+	// extra settings for tiles that come from an editor, like the properties of a Tiled object
+	pub props: Vec<(String, String)>,
+}
+
+impl LevTile {
+	pub fn get_prop(&self, name: &str) -> Option<&str> {
+		self.props.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+	}
+}
+
+
+
+async fn load_tiled_level(map_path: &str, glyphs: &str) -> Result<TiledLevel, String> {
+	let src = vfs::load_string(map_path).await.map_err(|e| e.to_string())?;
+	if map_path.ends_with(".json") || map_path.ends_with(".tmj") {
+		read_tiled_json(&src, glyphs, TILE_SIZE)
+	} else {
+		read_tmx(&src, glyphs, TILE_SIZE)
+	}
+}
+
+// what reacts to what, resolve_contacts has what happens then
+fn game_layer_matrix() -> LayerMatrix {
+	let mut matrix = LayerMatrix::new();
+	matrix.set(LAYER_PLAYER, LAYER_WALL | LAYER_BREAKABLE | LAYER_GHOST_BLOCK | LAYER_ENEMY | LAYER_BULLET | LAYER_TRIGGER, true);
+	matrix.set(LAYER_ENEMY, LAYER_BULLET | LAYER_TRIGGER, true);
+	matrix.set(LAYER_BULLET, LAYER_WALL | LAYER_BREAKABLE | LAYER_GHOST_BLOCK, true);
+	matrix
+}
+
+fn read_layout(parser: &StringParser) -> String {
+	let mut layout = parser.get_as_string_literal_or_def("layout", "P\n\n#").replace("\n\n", "\n");
+	layout.remove(0);
+	layout
 }
 
+// channel = 1, 3,4, 7,4; puts the switches, gates, traps and ghost blocks on those tiles on channel 1.
+// objects from Tiled can have a channel property instead
+fn read_channels(parser: &StringParser, grid_size: f32) -> Vec<(u32, Vec2)> {
+	let mut res = Vec::new();
+	for value in parser.get_all_strings("channel") {
+		let numbers = parse_int_list(&remove_whitespace(value));
+		let Some((channel, tiles)) = numbers.split_first() else {continue;};
+		for tile in tiles.chunks_exact(2) {
+			res.push(((*channel).max(0) as u32, vec2(tile[0] as f32, tile[1] as f32) * grid_size));
+		}
+	}
+	res
+}
 
+fn attach_channels(tiles: &mut [LevTile], channels: &[(u32, Vec2)]) {
+	for (channel, pos) in channels {
+		for tile in tiles.iter_mut().filter(|t| t.pos == *pos) {
+			tile.props.push((String::from("channel"), channel.to_string()));
+		}
+	}
+}
 
 fn read_level(layout: &str, grid_size: f32) -> Vec<LevTile> {
 	let mut tiles = Vec::new();
@@ -750,7 +1484,7 @@ fn read_level(layout: &str, grid_size: f32) -> Vec<LevTile> {
 			}
 			
 			_ => {
-				tiles.push(LevTile{pos:pos, ty:c.clone()});
+				tiles.push(LevTile{pos:pos, ty:c.clone(), props: Vec::new()});
 				//println!("{} -> {}", pos, c);
 				pos.x += grid_size;
 			}
@@ -784,6 +1518,27 @@ fn draw_background (texture: &Texture2D, rect: Option<Rect>, camera_pos: Vec2) {
 
 }
 
+// the part of the tile under the slope, cut out of source
+fn draw_slope(texture: &Texture2D, source: Rect, slope: &CollSlope) {
+	let min = slope.cbox.pos - slope.cbox.half_dim;
+	let size = slope.cbox.half_dim * 2.0;
+	let tex_size = texture.size();
+	let corners = [
+		vec2(0.0, size.y - slope.left), vec2(size.x, size.y - slope.right),
+		vec2(size.x, size.y), vec2(0.0, size.y),
+	];
+	let vertices = corners.iter().map(|c| {
+		let uv = (source.point() + *c / size * source.size()) / tex_size;
+		macroquad::models::Vertex {position: vec3(min.x + c.x, min.y + c.y, 0.0), uv, color: WHITE}
+	}).collect();
+
+	draw_mesh(&Mesh {
+		vertices,
+		indices: vec![0, 1, 2, 0, 2, 3],
+		texture: Some(texture.clone()),
+	});
+}
+
 fn get_level_size(layout: &str) -> UVec2 {
 	let mut res = UVec2::ZERO;
 	let iter = layout.split('\n');
@@ -801,3 +1556,35 @@ fn draw_coll_rect(rect: &CollBox, color: Color) {
 	draw_rectangle(draw_loc.x, draw_loc.y, rect.half_dim.x*2.0, rect.half_dim.y*2.0, color);
 }
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SWITCH_LEVEL: &str = include_str!("../assets/Levels/Test/SwitchLevel.par");
+
+	// every tile that got a channel, with it
+	fn wiring(tiles: &[LevTile]) -> Vec<(char, Vec2, String)> {
+		tiles.iter().filter_map(|t| t.get_prop("channel").map(|c| (t.ty, t.pos, c.to_string()))).collect()
+	}
+
+	#[test]
+	fn the_editor_keeps_the_channels() {
+		let parser = StringParser::new(SWITCH_LEVEL);
+		let layout = read_layout(&parser);
+		let channels = read_channels(&parser, TILE_SIZE);
+
+		// what load_level makes
+		let mut loaded = read_level(&layout, TILE_SIZE);
+		attach_channels(&mut loaded, &channels);
+		let loaded = wiring(&loaded);
+		assert_eq!(loaded.iter().filter(|(_, _, c)| c == "1").count(), 8);
+		assert!(loaded.contains(&('k', vec2(2.0, 2.0) * TILE_SIZE, String::from("2"))));
+		assert!(loaded.contains(&('D', vec2(16.0, 2.0) * TILE_SIZE, String::from("2"))));
+
+		// and what apply_editor makes from the same layout, going into the editor and back out
+		let editor = LevelEditor::new(&layout, "", false);
+		let mut edited = read_level(&editor.get_layout(), TILE_SIZE);
+		attach_channels(&mut edited, &channels);
+		assert_eq!(wiring(&edited), loaded);
+	}
+}
\ No newline at end of file
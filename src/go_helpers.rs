@@ -99,4 +99,26 @@ pub fn draw_centered_texture(texture: &Texture2D, pos: Vec2, color: Color, param
 		None => texture.size(),
 	} * 0.5;
 	draw_texture_ex(texture, draw_loc.x, draw_loc.y, color, params);
+}
+
+// xorshift for the tests, so every run checks the same cases and doesn't share macroquad's rand with other tests
+#[cfg(test)]
+pub struct TestRng(pub u64);
+
+#[cfg(test)]
+impl TestRng {
+	pub fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	pub fn below(&mut self, n: usize) -> usize {
+		(self.next() % n as u64) as usize
+	}
+
+	pub fn range(&mut self, low: f32, high: f32) -> f32 {
+		low + (self.next() >> 40) as f32 / (1u64 << 24) as f32 * (high - low)
+	}
 }
\ No newline at end of file
@@ -0,0 +1,120 @@
+// watches asset files for changes by polling their modification times,
+// turned on with hotReload = 1; in assets/options.par
+use std::time::SystemTime;
+
+use crate::vfs;
+
+const POLL_INTERVAL: f32 = 0.5;
+
+// what has to be redone for a changed file
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReloadKind {
+	Level,
+	Art,
+	Shader,
+	Language,
+}
+
+pub struct FileWatcher {
+	files: Vec<(String, Option<SystemTime>)>,
+	timer: f32,
+}
+
+impl FileWatcher {
+	pub fn new() -> Self {
+		Self {
+			files: Vec::new(),
+			timer: POLL_INTERVAL,
+		}
+	}
+
+	pub fn watch(&mut self, path: &str) {
+		if !self.files.iter().any(|(p, _)| p == path) {
+			self.files.push((path.to_string(), modified_time(path)));
+		}
+	}
+
+	// watches every file that is in the folder right now
+	pub fn watch_folder(&mut self, folder: &str) {
+		let Ok(entries) = std::fs::read_dir(vfs::root_path(folder)) else {return;};
+		let mut paths: Vec<String> = entries.flatten()
+			.filter(|e| e.path().is_file())
+			.map(|e| format!("{}/{}", folder, e.file_name().to_string_lossy()))
+			.collect();
+		paths.sort();
+		for path in paths {
+			self.watch(&path);
+		}
+	}
+
+	// returns the files that changed since the last poll, only looks at the disk every POLL_INTERVAL seconds
+	pub fn poll(&mut self, dt: f32) -> Vec<String> {
+		self.timer -= dt;
+		if self.timer > 0.0 {
+			return Vec::new();
+		}
+		self.timer = POLL_INTERVAL;
+
+		let mut changed = Vec::new();
+		for (path, time) in self.files.iter_mut() {
+			let new_time = modified_time(path);
+			if new_time != *time {
+				*time = new_time;
+				// files that are being written can be missing for a moment
+				if new_time.is_some() {
+					changed.push(path.clone());
+				}
+			}
+		}
+		changed
+	}
+}
+
+// the last thing that went wrong for each kind of reload, a kind is only cleared
+// when it reloads fine again so fixing a shader doesn't hide a broken image
+#[derive(Default)]
+pub struct ReloadErrors {
+	errors: Vec<(ReloadKind, String)>,
+}
+
+impl ReloadErrors {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn set(&mut self, kind: ReloadKind, error: String) {
+		self.clear(kind);
+		self.errors.push((kind, error));
+	}
+
+	pub fn clear(&mut self, kind: ReloadKind) {
+		self.errors.retain(|(k, _)| *k != kind);
+	}
+
+	// every error, one after another in the order they happened
+	pub fn get_text(&self) -> Option<String> {
+		if self.errors.is_empty() {
+			return None;
+		}
+		Some(self.errors.iter().map(|(_, e)| e.as_str()).collect::<Vec<&str>>().join("\n"))
+	}
+}
+
+pub fn reload_kind(path: &str, level_sources: &[String]) -> Option<ReloadKind> {
+	if level_sources.iter().any(|p| p == path) {
+		return Some(ReloadKind::Level);
+	}
+
+	let (folder, name) = path.rsplit_once('/').unwrap_or(("", path));
+	let extension = name.rsplit_once('.').map_or("", |(_, e)| e).to_lowercase();
+	match (folder, extension.as_str()) {
+		("assets/Shaders", _) => Some(ReloadKind::Shader),
+		("assets/Lang", "par") => Some(ReloadKind::Language),
+		("assets/Images", "png" | "par" | "fnt") => Some(ReloadKind::Art),
+		_ => None,
+	}
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+	std::fs::metadata(vfs::disk_path(path)).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,320 @@
+// in game level editor, Tab switches between editing and playing the level.
+//   left mouse paints, right mouse erases, middle mouse picks the glyph under the cursor
+//   Q / E or the mouse wheel go through the palette
+//   WASD or the arrow keys move the camera, ctrl + arrow keys resize the level
+//   Enter edits the note text (shift + Enter for a new line), ctrl + S saves the level file
+
+use macroquad::prelude::*;
+
+use crate::custom_text::*;
+use crate::parser::*;
+use crate::localization::*;
+
+const EDITOR_CAM_SPEED: f32 = 160.0;
+const EDITOR_FONT_SIZE: f32 = 0.5;
+const MIN_LEVEL_SIZE: UVec2 = uvec2(3, 3);
+
+const GRID_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.08);
+const BORDER_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.5);
+const CURSOR_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.35);
+
+// everything that can be painted, in the order the palette cycles through them.
+// the names are keys in the string tables
+pub const PALETTE: &[(char, &str)] = &[
+	('#', "Editor.Wall"),
+	('/', "Editor.BreakableWall"),
+	('=', "Editor.OneWayPlatform"),
+	('<', "Editor.SlopeUp"),
+	('>', "Editor.SlopeDown"),
+	('{', "Editor.GentleSlopeUpBottom"),
+	('[', "Editor.GentleSlopeUpTop"),
+	(']', "Editor.GentleSlopeDownTop"),
+	('}', "Editor.GentleSlopeDownBottom"),
+	('g', "Editor.GhostBlock"),
+	('*', "Editor.Spikes"),
+	('^', "Editor.Trap"),
+	('f', "Editor.FlyBox"),
+	('_', "Editor.PressurePlate"),
+	('L', "Editor.Lever"),
+	('k', "Editor.Key"),
+	('D', "Editor.Gate"),
+	('d', "Editor.OpenGate"),
+	('l', "Editor.Light"),
+	('E', "Editor.AngryGhost"),
+	('T', "Editor.Turret"),
+	('S', "Editor.Stalker"),
+	('p', "Editor.PlayerSpawn"),
+	('X', "Editor.Exit"),
+	('N', "Editor.Note"),
+];
+
+// glyphs a level can only have one of, placing them moves the old one
+const SINGLE_GLYPHS: &str = "pXN";
+
+pub struct LevelEditor {
+	grid: Vec<Vec<char>>,
+	selected: usize,
+	cursor: Option<UVec2>,
+
+	note_text: String,
+	// levels with a noteKey get their note from the string tables, so it can't be edited here
+	note_locked: bool,
+	editing_note: bool,
+
+	pub dirty: bool,
+	// a key in the string tables and whatever goes after it, like a path
+	message: String,
+	message_detail: String,
+}
+
+impl LevelEditor {
+	pub fn new(layout: &str, note_text: &str, note_locked: bool) -> Self {
+		let mut grid: Vec<Vec<char>> = layout.split('\n').map(|row| row.chars().collect()).collect();
+		let width = grid.iter().map(|row| row.len()).max().unwrap_or(0);
+		for row in grid.iter_mut() {
+			row.resize(width, ' ');
+		}
+
+		let mut editor = Self {
+			grid,
+			selected: 0,
+			cursor: None,
+			note_text: note_text.to_string(),
+			note_locked,
+			editing_note: false,
+			dirty: false,
+			message: String::new(),
+			message_detail: String::new(),
+		};
+		editor.resize(editor.get_size().max(MIN_LEVEL_SIZE));
+		editor
+	}
+
+	pub fn get_size(&self) -> UVec2 {
+		uvec2(self.grid.first().map_or(0, |row| row.len()) as u32, self.grid.len() as u32)
+	}
+
+	pub fn resize(&mut self, size: UVec2) {
+		let size = size.max(MIN_LEVEL_SIZE);
+		self.grid.resize(size.y as usize, Vec::new());
+		for row in self.grid.iter_mut() {
+			row.resize(size.x as usize, ' ');
+		}
+	}
+
+	// the layout the way load_level reads it
+	pub fn get_layout(&self) -> String {
+		let rows: Vec<String> = self.grid.iter().map(|row| row.iter().collect()).collect();
+		rows.join("\n")
+	}
+
+	pub fn get_note_text(&self) -> &str {
+		&self.note_text
+	}
+
+	pub fn is_typing(&self) -> bool {
+		self.editing_note
+	}
+
+	pub fn set_message(&mut self, key: &str, detail: &str) {
+		self.message = key.to_string();
+		self.message_detail = detail.to_string();
+	}
+
+	fn set_tile(&mut self, tile: UVec2, glyph: char) {
+		if SINGLE_GLYPHS.contains(glyph) {
+			for row in self.grid.iter_mut() {
+				for c in row.iter_mut() {
+					if *c == glyph {*c = ' ';}
+				}
+			}
+		}
+
+		let cell = &mut self.grid[tile.y as usize][tile.x as usize];
+		if *cell != glyph {
+			*cell = glyph;
+			self.dirty = true;
+		}
+	}
+
+	// returns true when the level changed and has to be rebuilt
+	pub fn update(&mut self, mouse_world: Vec2, tile_size: f32, cam_position: &mut Vec2, dt: f32) -> bool {
+		if self.editing_note {
+			self.type_note();
+			return false;
+		}
+
+		// camera
+		let mut pan = Vec2::ZERO;
+		let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+		if !ctrl {
+			if is_key_down(KeyCode::A) || is_key_down(KeyCode::Left) {pan.x -= 1.0;}
+			if is_key_down(KeyCode::D) || is_key_down(KeyCode::Right) {pan.x += 1.0;}
+			if is_key_down(KeyCode::W) || is_key_down(KeyCode::Up) {pan.y -= 1.0;}
+			if is_key_down(KeyCode::S) || is_key_down(KeyCode::Down) {pan.y += 1.0;}
+		}
+		*cam_position += pan * EDITOR_CAM_SPEED * dt;
+
+		// palette
+		let wheel = mouse_wheel().1;
+		if is_key_pressed(KeyCode::E) || wheel < 0.0 {
+			self.selected = (self.selected + 1) % PALETTE.len();
+		}
+		if is_key_pressed(KeyCode::Q) || wheel > 0.0 {
+			self.selected = (self.selected + PALETTE.len() - 1) % PALETTE.len();
+		}
+
+		let mut changed = false;
+
+		// resizing
+		if ctrl {
+			let size = self.get_size().as_ivec2();
+			let mut new_size = size;
+			if is_key_pressed(KeyCode::Left) {new_size.x -= 1;}
+			if is_key_pressed(KeyCode::Right) {new_size.x += 1;}
+			if is_key_pressed(KeyCode::Up) {new_size.y -= 1;}
+			if is_key_pressed(KeyCode::Down) {new_size.y += 1;}
+			if new_size != size {
+				self.resize(new_size.max(MIN_LEVEL_SIZE.as_ivec2()).as_uvec2());
+				self.dirty = true;
+				changed = true;
+			}
+		}
+
+		// painting
+		let tile = ((mouse_world / tile_size) + Vec2::splat(0.5)).floor();
+		let size = self.get_size();
+		self.cursor = if tile.x >= 0.0 && tile.y >= 0.0 && (tile.x as u32) < size.x && (tile.y as u32) < size.y {
+			Some(tile.as_uvec2())
+		} else {
+			None
+		};
+
+		if let Some(tile) = self.cursor {
+			let cell = self.grid[tile.y as usize][tile.x as usize];
+			if is_mouse_button_down(MouseButton::Left) && cell != PALETTE[self.selected].0 {
+				self.set_tile(tile, PALETTE[self.selected].0);
+				changed = true;
+			}
+			if is_mouse_button_down(MouseButton::Right) && cell != ' ' {
+				self.set_tile(tile, ' ');
+				changed = true;
+			}
+			// picks the glyph under the cursor
+			if is_mouse_button_pressed(MouseButton::Middle) {
+				if let Some(idx) = PALETTE.iter().position(|(c, _)| *c == cell) {
+					self.selected = idx;
+				}
+			}
+		}
+
+		if is_key_pressed(KeyCode::Enter) {
+			if self.note_locked {
+				self.set_message("Editor.NoteLocked", "");
+			} else {
+				self.editing_note = true;
+				self.set_message("", "");
+				// clears the queue so the key that opened the note doesn't get typed
+				while get_char_pressed().is_some() {}
+			}
+		}
+
+		changed
+	}
+
+	fn type_note(&mut self) {
+		let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+		if is_key_pressed(KeyCode::Enter) {
+			if shift {
+				self.note_text.push('\n');
+			} else {
+				self.editing_note = false;
+			}
+			self.dirty = true;
+		}
+		if is_key_pressed(KeyCode::Backspace) {
+			self.note_text.pop();
+			self.dirty = true;
+		}
+
+		while let Some(c) = get_char_pressed() {
+			// ; ends a value in a level file so it can't be in a note
+			if !c.is_control() && c != ';' {
+				self.note_text.push(c);
+				self.dirty = true;
+			}
+		}
+	}
+
+	// writes the layout and note into the level file, keeping everything else in it
+	pub fn save(&mut self, level_path: &str) -> Result<(), String> {
+		let src = std::fs::read_to_string(level_path).unwrap_or_default();
+		let newline = if src.contains('\r') {"\r\n"} else {"\n"};
+
+		let layout = format!("{}{}", newline, self.get_layout().replace('\n', newline));
+		let mut res = replace_value(&src, "layout", &layout);
+		if !self.note_locked {
+			let note = self.note_text.replace('\n', newline);
+			res = if note.is_empty() {
+				remove_value(&res, "noteText")
+			} else {
+				replace_value(&res, "noteText", &note)
+			};
+		}
+
+		std::fs::write(level_path, res).map_err(|e| e.to_string())?;
+		self.dirty = false;
+		Ok(())
+	}
+
+	pub fn draw(&self, font: &RFont, localization: &Localization, tile_size: f32, cam_position: Vec2, cam_dim: Vec2, time: f32) {
+		let size = self.get_size().as_vec2() * tile_size;
+		let origin = -Vec2::splat(tile_size * 0.5);
+
+		// grid
+		let view_min = ((cam_position - cam_dim - origin) / tile_size).floor().max(Vec2::ZERO);
+		let view_max = ((cam_position + cam_dim - origin) / tile_size).ceil().min(self.get_size().as_vec2());
+		let mut x = view_min.x;
+		while x <= view_max.x {
+			let line_x = origin.x + x * tile_size;
+			draw_line(line_x, origin.y, line_x, origin.y + size.y, 0.5, GRID_COLOR);
+			x += 1.0;
+		}
+		let mut y = view_min.y;
+		while y <= view_max.y {
+			let line_y = origin.y + y * tile_size;
+			draw_line(origin.x, line_y, origin.x + size.x, line_y, 0.5, GRID_COLOR);
+			y += 1.0;
+		}
+		draw_rectangle_lines(origin.x, origin.y, size.x, size.y, 1.0, BORDER_COLOR);
+
+		if let Some(tile) = self.cursor {
+			let pos = tile.as_vec2() * tile_size + origin;
+			draw_rectangle(pos.x, pos.y, tile_size, tile_size, CURSOR_COLOR);
+		}
+
+		// info
+		let (glyph, name) = PALETTE[self.selected];
+		let dim = self.get_size();
+		let mut info = format!("{}  {} {}  {}X{}{}", localization.get_or_key("Editor.Title"), glyph.to_string().replace('{', "{{"),
+			localization.get_or_key(name), dim.x, dim.y, if self.dirty {"  *"} else {""});
+		if self.editing_note {
+			info.push_str(&format!("\n{} ", localization.get_or_key("Editor.NoteText")));
+			info.push_str(&self.note_text.replace('{', "{{"));
+			info.push('_');
+		} else if !self.message.is_empty() {
+			info.push('\n');
+			info.push_str(&localization.get_or_key(&self.message));
+			if !self.message_detail.is_empty() {
+				info.push(' ');
+				info.push_str(&self.message_detail.replace('{', "{{"));
+			}
+		}
+
+		let corner = cam_position - cam_dim + Vec2::splat(4.0);
+		let para = font.output_para(&info);
+		let line_height = font.get_line_height() * EDITOR_FONT_SIZE;
+		draw_rectangle(corner.x - 4.0, corner.y - 4.0, cam_dim.x * 2.0, line_height * para.len() as f32 + 6.0, Color::new(0.0, 0.0, 0.0, 0.5));
+		font.draw_derived_para(corner, WHITE, EDITOR_FONT_SIZE, &para, TextAlign::Left, time);
+	}
+}
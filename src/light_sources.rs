@@ -4,54 +4,92 @@ use crate::wall_man::*;
 use crate::options::*;
 
 use crate::go_helpers::*;
+use crate::game_world::AssetManager;
 
 use macroquad::prelude::*;
 
+// how far the light of a lamp and a window goes, matches the textures in draw_lights
+const LAMP_REACH: f32 = 100.0;
+const LAMP_TOP_WIDTH: f32 = 8.0;
+const LAMP_BOTTOM_WIDTH: f32 = 48.0;
+const WINDOW_REACH: f32 = 64.0;
 
 
+// lights under a ceiling are lamps hanging from it, the rest are windows
 pub fn assign_light_type (light_pos: Vec2, world_man: &[MultiCollider<CollBox>]) -> char {
-	if MultiCollider::check_intersection_slice(&world_man, &collbox!(light_pos + vec2(0.0, -16.0), Vec2::ZERO)) {
+	if MultiCollider::raycast_slice(world_man, light_pos, vec2(0.0, -1.0), 16.0).is_some() {
 		'c'
 	} else {
 		'w'
 	}
 }
 
-pub fn draw_light_sources(lights: &[(Vec2, char)], tex: &Texture2D) {
+// how well something at pos can be seen, from the AMBIENT_LIGHT in the dark up to 1 right under a lamp.
+// about what draw_lights puts there, and walls block the light like the shadows do
+pub fn visibility(lights: &[(Vec2, char)], walls: &[MultiCollider<CollBox>], pos: Vec2) -> f32 {
+	let mut light = brightness(AMBIENT_LIGHT);
+	for &(light_pos, light_type) in lights {
+		let offset = pos - light_pos;
+		let amount = match light_type {
+			// the cone gets wider the further down it goes
+			'c' => {
+				let depth = offset.y / LAMP_REACH;
+				let half_width = LAMP_TOP_WIDTH + (LAMP_BOTTOM_WIDTH - LAMP_TOP_WIDTH) * depth;
+				if !(0.0..1.0).contains(&depth) {0.0} else {(1.0 - depth) * (1.0 - offset.x.abs() / half_width).max(0.0)}
+			}
+
+			_ => (1.0 - offset.length() / WINDOW_REACH) * brightness(WINDOW_LIGHT),
+		};
+		if amount > 0.0 && line_of_sight(walls, light_pos, pos) {
+			light += amount;
+		}
+	}
+	light.min(1.0)
+}
+
+fn brightness(color: Color) -> f32 {
+	(color.r + color.g + color.b) / 3.0
+}
+
+pub fn draw_light_sources(lights: &[(Vec2, char)], assets: &AssetManager) {
+	let (lamp_tex, lamp) = assets.sprite("lamp");
+	let (window_tex, window) = assets.sprite("window");
 	for i in 0..lights.len() {
 		let (light_pos, light_type) = lights[i];
 		match light_type {
-			'c' => draw_centered_texture(&tex, light_pos, EXIT_COLOR, DrawTextureParams{
+			'c' => draw_centered_texture(lamp_tex, light_pos, EXIT_COLOR, DrawTextureParams{
 				dest_size: Some(vec2(16.0, 16.0)),
-				source: Some(Rect::new(96.0, 80.0, 16.0, 16.0)),
+				source: Some(lamp),
 				..Default::default()
 			}),
 
-			_ => draw_centered_texture(&tex, light_pos, WINDOW_COLOR, DrawTextureParams{
+			_ => draw_centered_texture(window_tex, light_pos, WINDOW_COLOR, DrawTextureParams{
 				dest_size: Some(vec2(16.0, 16.0)),
-				source: Some(Rect::new(96.0, 64.0, 16.0, 16.0)),
+				source: Some(window),
 				..Default::default()
 			}),
 		}
 	}
 }
 
-pub fn draw_lights(lights: &[(Vec2, char)], tex:&Texture2D) {
+pub fn draw_lights(lights: &[(Vec2, char)], assets: &AssetManager) {
+	let (cone_tex, cone) = assets.sprite("light_cone");
+	let (round_tex, round) = assets.sprite("light_round");
 	for i in 0..lights.len() {
 		let (light_pos, light_type) = lights[i];
 		match light_type {
 			'c' => {
 				let light_size = vec2(64.0+32.0, 64.0+48.0);
-				draw_centered_texture(&tex, light_pos + vec2(0.0, light_size.y * 0.5-12.0), WHITE, DrawTextureParams{
+				draw_centered_texture(cone_tex, light_pos + vec2(0.0, light_size.y * 0.5-12.0), WHITE, DrawTextureParams{
 					dest_size: Some(light_size),
-					source: Some(Rect::new(33.0, 0.0, 32.0, 32.0)),
+					source: Some(cone),
 					..Default::default()
 				})
 			}
 
-			_ => draw_centered_texture(&tex, light_pos, WINDOW_LIGHT, DrawTextureParams{
+			_ => draw_centered_texture(round_tex, light_pos, WINDOW_LIGHT, DrawTextureParams{
 				dest_size: Some(Vec2::splat(128.0)),
-				source: Some(Rect::new(0.0, 0.0, 32.0, 32.0)),
+				source: Some(round),
 				..Default::default()
 			}),
 		}
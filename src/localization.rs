@@ -0,0 +1,85 @@
+use crate::parser::*;
+use crate::vfs;
+
+pub const DEFAULT_LANGUAGE: &str = "English";
+
+pub fn create_language_path(language: &str) -> String {
+	format!("assets/Lang/{}.par", language)
+}
+
+// looks strings up in the selected language, and falls back to English for missing keys
+pub struct Localization {
+	language: String,
+	table: Option<StringParser>,
+	fallback: StringParser,
+}
+
+impl Localization {
+	pub async fn load(language: &str) -> Self {
+		let fallback = match vfs::load_string(&create_language_path(DEFAULT_LANGUAGE)).await {
+			Ok(s) => StringParser::new(&s),
+			Err(_) => {
+				println!("Error. The {} string table is missing", DEFAULT_LANGUAGE);
+				StringParser::new("")
+			}
+		};
+
+		let table = if language == DEFAULT_LANGUAGE {
+			None
+		} else {
+			match vfs::load_string(&create_language_path(language)).await {
+				Ok(s) => Some(StringParser::new(&s)),
+				Err(_) => {
+					println!("Error. No string table for {}, using {}", language, DEFAULT_LANGUAGE);
+					None
+				}
+			}
+		};
+
+		Self {
+			language: language.to_string(),
+			table,
+			fallback,
+		}
+	}
+
+	pub fn get(&self, key: &str) -> Option<String> {
+		let value = match &self.table {
+			Some(table) => table.get_string(key).or_else(|| self.fallback.get_string(key)),
+			None => self.fallback.get_string(key),
+		}?;
+
+		Some(clean_value(value))
+	}
+
+	// shows the key itself when no language has the string, so it is easy to spot
+	pub fn get_or_key(&self, key: &str) -> String {
+		match self.get(key) {
+			Some(s) => s,
+			None => key.to_string(),
+		}
+	}
+
+	pub fn get_language(&self) -> &str {
+		&self.language
+	}
+}
+
+fn clean_value(value: &str) -> String {
+	value.trim().replace("\n\n", "\n")
+}
+
+// keys that are in the base table but not in the translation
+#[allow(dead_code)]
+pub fn find_missing_keys(base: &StringParser, translation: &StringParser) -> Vec<String> {
+	base.get_names().into_iter()
+		.filter(|key| translation.get_string(key).is_none_or(|v| v.trim().is_empty()))
+		.map(|key| key.to_string())
+		.collect()
+}
+
+// keys in the translation that the base table doesn't have, usually typos or removed strings
+#[allow(dead_code)]
+pub fn find_unused_keys(base: &StringParser, translation: &StringParser) -> Vec<String> {
+	find_missing_keys(translation, base)
+}
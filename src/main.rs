@@ -5,13 +5,16 @@ pub mod parser;
 pub mod enemy;
 pub mod animation;
 pub mod collision;
+mod spatial_hash;
 pub mod go_helpers;
 pub mod game_world;
 pub mod player;
 pub mod options;
 mod wall_man;
+mod moving_platform;
+mod channels;
+mod noise;
 pub mod staticobj;
-pub mod StaticObj;
 pub mod partical_system;
 mod custom_text;
 
@@ -19,6 +22,18 @@ mod custom_shader;
 
 mod light_sources;
 
+mod localization;
+
+mod tiled;
+
+mod level_editor;
+
+mod hot_reload;
+
+mod vfs;
+
+mod pack_menu;
+
 use light_sources::*;
 
 use custom_shader::*;
@@ -32,6 +47,9 @@ use macroquad::{material, prelude::*};
 use game_world::{AssetManager, GameWorld};
 use options::{AMBIENT_LIGHT, CAM_DIM, CAM_HEIGHT, CAM_WIDTH, LIGHT_DIV, SCREEN_DIM, SHADOW_CAM_DIM};
 use partical_system::Partical;
+use parser::remove_whitespace;
+use hot_reload::{reload_kind, FileWatcher, ReloadErrors, ReloadKind};
+use pack_menu::{PackMenu, PackMenuAction};
 
 fn window_setup() -> Conf {
 	Conf {
@@ -61,7 +79,7 @@ async fn main() {
 
 async fn run() -> Result<(), String>{
 	let mut fullscreen: bool = false;
-	//set_pc_assets_folder("assets");
+	vfs::mount()?;
 
 	macro_rules! safe_unwrap {
 		($res:expr) => {
@@ -76,35 +94,29 @@ async fn run() -> Result<(), String>{
 		};
 	}
 
-	macro_rules! s_load_text {
-		($fname:expr) => {
-			match load_string($fname).await {
-				Ok(s) => {s},
-
-				Err(_) => {return Result::Err(($fname.to_string()))}
-			}
+	// settings
+	let options = parser::StringParser::new(&vfs::load_string("assets/options.par").await.unwrap_or_default());
+	if let Some(pack) = options.get_string("pack").map(|p| remove_whitespace(p)).filter(|p| !p.is_empty()) {
+		if vfs::find_packs().contains(&pack) {
+			vfs::set_active_pack(Some(pack));
+		} else {
+			println!("There is no level pack called {}", pack);
 		}
 	}
 
 	// load assets & set up world
-	let campaign = parser::StringParser::new(s_load_text!("assets/Levels/Campaign.par").as_str());
-
-	let level_file_names: Vec<String> = campaign.get_as_strings("Levels");
-	
-	if campaign.get_int_or_def("printLevelNames", 0) == 1 {
-		for i in 0..level_file_names.len() {
-			print!("{}, ", level_file_names[i]);
-		}
-		println!();
-	}
-	
-	let assets = AssetManager::new().await;
-	let mut world = GameWorld::new(assets);
-	{
-		let start_ind = campaign.get_int_or_def("startAt", 0).abs();
-		world.level_index = start_ind;
-		world.load_level(create_level_path(level_file_names[start_ind as usize].as_str()).as_str()).await;
+	let (mut level_file_names, start_ind) = load_campaign().await?;
+	let mut languages = options.get_as_strings("languages");
+	let language = options.get_string_or_def("language", localization::DEFAULT_LANGUAGE.to_string());
+	let language = remove_whitespace(&language);
+	if !languages.contains(&language) {
+		languages.push(language.clone());
 	}
+
+	let assets = AssetManager::new().await.map_err(|e| e.to_string())?;
+	let mut world = GameWorld::new(assets, localization::Localization::load(&language).await);
+	world.level_index = start_ind;
+	world.load_level(create_level_path(level_file_names[start_ind as usize].as_str()).as_str()).await;
 	world.setup();
 
 	// setup render texture and camera
@@ -127,16 +139,81 @@ async fn run() -> Result<(), String>{
 		..Default::default()
 	};
 
-	let light_mat = create_light_mat();
-	let shadow_mat = create_shadow_mat();
-	let screen_mat = create_screen_mat();
+	let mut materials = Materials::new(&ShaderSources::built_in())?;
+
+	// hot reloading
+	let hot_reload = options.get_int_or_def("hotReload", 0) == 1;
+	let mut watcher = FileWatcher::new();
+	if hot_reload {
+		watcher.watch_folder("assets/Images");
+		watcher.watch_folder(SHADER_FOLDER);
+		watcher.watch_folder("assets/Lang");
+	}
+	// a shader that doesn't compile or an image that can't be loaded keeps the old one
+	// and shows this until it's fixed
+	let mut reload_errors = ReloadErrors::new();
+
+	let mut pack_menu: Option<PackMenu> = None;
+
 	// loop
 	loop {
-		if is_key_pressed(KeyCode::O){
-			world.set_assets(AssetManager::new().await);
+		let typing = world.is_typing();
+
+		if hot_reload {
+			for source in world.get_level_sources().to_vec() {
+				watcher.watch(&source);
+			}
+			let kinds: Vec<ReloadKind> = watcher.poll(get_frame_time()).iter()
+				.filter_map(|path| reload_kind(path, world.get_level_sources()))
+				.collect();
+
+			// the editor writes the level file itself, so it doesn't get restarted under it
+			if kinds.contains(&ReloadKind::Level) && !world.is_editing() {
+				world.reload_level().await;
+			}
+			// art doesn't touch the level, so everything stays where it is
+			if kinds.contains(&ReloadKind::Art) {
+				match AssetManager::new().await {
+					Ok(assets) => {
+						world.set_assets(assets);
+						reload_errors.clear(ReloadKind::Art);
+					}
+					Err(e) => reload_errors.set(ReloadKind::Art, e.to_string()),
+				}
+			}
+			if kinds.contains(&ReloadKind::Language) {
+				let language = world.get_language().to_string();
+				world.set_localization(localization::Localization::load(&language).await);
+			}
+			if kinds.contains(&ReloadKind::Shader) {
+				match ShaderSources::load().await.and_then(|sources| Materials::new(&sources)) {
+					Ok(new_materials) => {
+						materials = new_materials;
+						reload_errors.clear(ReloadKind::Shader);
+					}
+					Err(e) => reload_errors.set(ReloadKind::Shader, e),
+				}
+			}
+		}
+
+		if is_key_pressed(KeyCode::O) && !typing {
+			match AssetManager::new().await {
+				Ok(assets) => {
+					world.set_assets(assets);
+					reload_errors.clear(ReloadKind::Art);
+				}
+				Err(e) => reload_errors.set(ReloadKind::Art, e.to_string()),
+			}
 			world.load_level(&create_level_path(&level_file_names[world.level_index as usize])).await;
 		}
 
+		// cycles through the languages in options.par
+		if is_key_pressed(KeyCode::F2) && !typing {
+			let current = languages.iter().position(|l| l == world.get_language()).unwrap_or(0);
+			let next = &languages[(current + 1) % languages.len()];
+			world.set_localization(localization::Localization::load(next).await);
+		}
+
 		if is_key_pressed(KeyCode::F11) {
 			fullscreen = !fullscreen;
 			set_fullscreen(fullscreen);
@@ -146,10 +223,64 @@ async fn run() -> Result<(), String>{
 			break;
 		}*/
 
-		if get_frame_time() > 0.0001 {
-			world.update(get_frame_time().min(0.15));
+		// level packs
+		if is_key_pressed(KeyCode::F3) && !typing && pack_menu.is_none() {
+			pack_menu = Some(PackMenu::new().await);
+		} else if let Some(menu) = &mut pack_menu {
+			match menu.update() {
+				PackMenuAction::Stay => {}
+
+				PackMenuAction::Close => pack_menu = None,
+
+				PackMenuAction::Choose(pack) => {
+					pack_menu = None;
+					let previous = vfs::get_active_pack();
+					vfs::set_active_pack(pack);
+					match load_campaign().await {
+						Ok((names, start)) => {
+							level_file_names = names;
+							reload_errors.clear(ReloadKind::Level);
+							match AssetManager::new().await {
+								Ok(assets) => {
+									world.set_assets(assets);
+									reload_errors.clear(ReloadKind::Art);
+								}
+								Err(e) => reload_errors.set(ReloadKind::Art, e.to_string()),
+							}
+							let language = world.get_language().to_string();
+							world.set_localization(localization::Localization::load(&language).await);
+							world.level_index = start;
+							world.load_level(&create_level_path(&level_file_names[start as usize])).await;
+							world.setup();
+						}
+
+						Err(e) => {
+							// the campaign of the pack couldn't be read
+							vfs::set_active_pack(previous);
+							reload_errors.set(ReloadKind::Level, e);
+						}
+					}
+				}
+			}
+		}
+
+		// level editor
+		if is_key_pressed(KeyCode::Tab) && !typing && pack_menu.is_none() {
+			world.toggle_editor();
+		}
+
+		if pack_menu.is_some() {
+			// the game waits while picking a pack
+		} else if world.is_editing() {
+			let (screen_pos, screen_scale) = scale_to_fit(screen.texture.size(), vec2(screen_width(), screen_height()));
+			let mouse_world = screen_to_world(Vec2::from(mouse_position()), screen_pos, screen_scale, camera.target);
+			world.update_editor(mouse_world, get_frame_time().min(0.15));
+		} else {
+			if get_frame_time() > 0.0001 {
+				world.update(get_frame_time().min(0.15));
+			}
+			world.handle_levels(&level_file_names).await;
 		}
-		world.handle_levels(&level_file_names).await;
 		camera.target = (world.cam_position * 2.0).round() / 2.0;
 		shadow_cam.target = (world.cam_position * 2.0).round() / 2.0;
 		
@@ -160,14 +291,14 @@ async fn run() -> Result<(), String>{
 		// draws lights
 		set_camera(&shadow_cam);
 		clear_background(AMBIENT_LIGHT);
-		gl_use_material(&light_mat);
+		gl_use_material(&materials.light);
 		world.draw_lights();
 		gl_use_default_material();
 		set_camera(&camera);
 		// draw to the render_texture
 		world.draw();
 
-		gl_use_material(&shadow_mat);
+		gl_use_material(&materials.shadow);
 		draw_centered_texture(&shadow_map.texture, world.cam_position, WHITE, DrawTextureParams{
 			dest_size: Some(SCREEN_DIM.as_vec2()),
 			..Default::default()
@@ -175,21 +306,26 @@ async fn run() -> Result<(), String>{
 		gl_use_default_material();
 		
 		world.draw_hud();
+		if let Some(menu) = &pack_menu {
+			world.draw_pack_menu(menu);
+		}
 		
 		//draw_text(format!("FPS: {}", get_fps()).as_str(), world.player.cbox.pos.x, world.player.cbox.pos.y, 16.0, RED);
 		// draw_texture to screen
 		set_default_camera();
 		let(screen_pos, screen_scale) = scale_to_fit(screen.texture.size(), vec2(screen_width(), screen_height()));
-		screen_mat.set_uniform("screenSize", vec2(screen_width(), screen_height()));
-		gl_use_material(&screen_mat);
+		materials.screen.set_uniform("screenSize", vec2(screen_width(), screen_height()));
+		gl_use_material(&materials.screen);
 		draw_texture_ex(&screen.texture, screen_pos.x, screen_pos.y, WHITE, DrawTextureParams{
 			dest_size: Some(screen.texture.size() * screen_scale),
 			..Default::default()
 		});
 		gl_use_default_material();
-		
-		
-		
+
+		if let Some(e) = reload_errors.get_text() {
+			draw_error_text(&e);
+		}
+
 
 		next_frame().await;
 	}
@@ -197,6 +333,40 @@ async fn run() -> Result<(), String>{
 	return Result::Ok(());
 }
 
+fn draw_error_text(error: &str) {
+	let lines: Vec<&str> = error.lines().collect();
+	draw_rectangle(0.0, 0.0, screen_width(), 6.0 + lines.len() as f32 * 18.0, Color::new(0.0, 0.0, 0.0, 0.75));
+	for (i, line) in lines.iter().enumerate() {
+		draw_text(line, 6.0, 18.0 + i as f32 * 18.0, 20.0, RED);
+	}
+}
+
+const CAMPAIGN_PATH: &str = "assets/Levels/Campaign.par";
+
+// the level list and the level to start at, from the active pack's campaign if there is one
+async fn load_campaign() -> Result<(Vec<String>, i32), String> {
+	let campaign = match vfs::load_string(CAMPAIGN_PATH).await {
+		Ok(s) => parser::StringParser::new(&s),
+
+		Err(_) => return Err(vfs::disk_path(CAMPAIGN_PATH)),
+	};
+
+	let level_file_names: Vec<String> = campaign.get_as_strings("Levels");
+	if level_file_names.is_empty() {
+		return Err(format!("{} has no levels", vfs::disk_path(CAMPAIGN_PATH)));
+	}
+
+	if campaign.get_int_or_def("printLevelNames", 0) == 1 {
+		for i in 0..level_file_names.len() {
+			print!("{}, ", level_file_names[i]);
+		}
+		println!();
+	}
+
+	let start_ind = campaign.get_int_or_def("startAt", 0).abs().min(level_file_names.len() as i32 - 1);
+	Ok((level_file_names, start_ind))
+}
+
 fn create_level_path (level_name: &str) -> String {
 	format!("assets/Levels/{}.par", level_name)
 }
@@ -213,6 +383,12 @@ fn scale_to_fit(src_size: Vec2, dest_size:Vec2) ->(Vec2, f32) {
 	}
 }
 
+// turns a point on the window into a point in the game world
+fn screen_to_world(point: Vec2, screen_pos: Vec2, screen_scale: f32, cam_target: Vec2) -> Vec2 {
+	let texture_point = (point - screen_pos) / screen_scale;
+	cam_target + (texture_point / SCREEN_DIM.as_vec2() - Vec2::splat(0.5)) * 2.0 * CAM_DIM
+}
+
 /*fn calc_viewport(src_size:Vec2, dest_size:Vec2) ->(i32, i32, i32, i32) {
 	let(pos, scale) = scale_to_fit(src_size, dest_size);
 	let f_dest_size = dest_size * scale;
@@ -0,0 +1,129 @@
+// platforms that go along a path from the level file and carry whatever stands on them.
+// movingPlatform = pingpong, enemies, 40, 3, 2,5, 10,5;
+// the words can go anywhere, loop (the default) goes back to the first point after the last and
+// pingpong turns around at both ends, enemies makes it carry enemies too. the numbers are the speed
+// in pixels a second, the width in tiles and then the tiles the left end goes through
+
+use macroquad::prelude::*;
+
+use crate::collision::*;
+use crate::parser::*;
+
+pub const MOVING_PLATFORM_THICKNESS: f32 = 8.0;
+// enemies this far over a platform ride it, they float so they never really stand on it
+const ENEMY_CARRY_HEIGHT: f32 = 16.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PathMode {
+	Loop,
+	PingPong,
+}
+
+#[derive(Clone)]
+pub struct PlatformPath {
+	// where the middle of the platform goes
+	points: Vec<Vec2>,
+	mode: PathMode,
+	speed: f32,
+	width: f32,
+	carries_enemies: bool,
+}
+
+impl PlatformPath {
+	pub fn parse(value: &str, tile_size: f32) -> Result<Self, String> {
+		let mut mode = PathMode::Loop;
+		let mut carries_enemies = false;
+		let mut numbers = Vec::new();
+		for part in remove_whitespace(value).split(',').filter(|p| !p.is_empty()) {
+			match part {
+				"loop" => mode = PathMode::Loop,
+				"pingpong" => mode = PathMode::PingPong,
+				"enemies" => carries_enemies = true,
+				_ if part.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') => numbers.push(parse_float(part)),
+				_ => return Err(format!("{} isn't loop, pingpong or enemies", part)),
+			}
+		}
+
+		let [speed, width, points @ ..] = numbers.as_slice() else {
+			return Err(String::from("needs a speed and a width"));
+		};
+		if *speed <= 0.0 {
+			return Err(format!("speed {} has to be more than 0", speed));
+		}
+		if points.len() < 2 || points.len() % 2 != 0 {
+			return Err(String::from("needs at least one point and every point needs an x and a y"));
+		}
+		let width = width.max(1.0) * tile_size;
+		// the tiles are the left end of the platform, it sits in the top of them
+		let offset = vec2(width - tile_size, MOVING_PLATFORM_THICKNESS - tile_size) * 0.5;
+		Ok(Self {
+			points: points.chunks(2).map(|p| vec2(p[0], p[1]) * tile_size + offset).collect(),
+			mode,
+			speed: *speed,
+			width,
+			carries_enemies,
+		})
+	}
+}
+
+pub struct MovingPlatform {
+	pub cbox: CollBox,
+	// how far it moved in the last update
+	pub motion: Vec2,
+	path: PlatformPath,
+	next: usize,
+	forward: bool,
+}
+
+impl MovingPlatform {
+	pub fn new(path: PlatformPath) -> Self {
+		Self {
+			cbox: collbox!(path.points[0], vec2(path.width, MOVING_PLATFORM_THICKNESS) * 0.5),
+			motion: Vec2::ZERO,
+			next: 1 % path.points.len(),
+			forward: true,
+			path,
+		}
+	}
+
+	pub fn update(&mut self, dt: f32) {
+		let start = self.cbox.pos;
+		let mut left = self.path.speed * dt;
+		// a fast platform can go past more than one point in a frame, but not round a path with no length forever
+		for _ in 0..self.path.points.len() * 2 {
+			let target = self.path.points[self.next];
+			let distance = self.cbox.pos.distance(target);
+			if distance > left {
+				self.cbox.pos += (target - self.cbox.pos) / distance * left;
+				break;
+			}
+			self.cbox.pos = target;
+			left -= distance;
+			self.advance();
+		}
+		self.motion = self.cbox.pos - start;
+	}
+
+	fn advance(&mut self) {
+		let last = self.path.points.len() - 1;
+		match self.path.mode {
+			PathMode::Loop => self.next = if self.next >= last {0} else {self.next + 1},
+			PathMode::PingPong => {
+				if last == 0 {
+					return;
+				}
+				if (self.forward && self.next >= last) || (!self.forward && self.next == 0) {
+					self.forward = !self.forward;
+				}
+				self.next = if self.forward {self.next + 1} else {self.next - 1};
+			}
+		}
+	}
+
+	// where enemies get carried, None when it doesn't carry them
+	pub fn carry_zone(&self) -> Option<CollBox> {
+		self.path.carries_enemies.then(|| {
+			collbox!(self.cbox.pos - vec2(0.0, ENEMY_CARRY_HEIGHT * 0.5), self.cbox.half_dim + vec2(0.0, ENEMY_CARRY_HEIGHT * 0.5))
+		})
+	}
+}
@@ -0,0 +1,67 @@
+// sounds enemies can hear. a noise is loudest where it was made and quiet at its radius,
+// every pixel of wall between it and the one listening takes a bit more off
+
+use macroquad::prelude::*;
+
+use crate::collision::*;
+use crate::staticobj::TRAP_ANIM_TIME;
+
+pub const TRAP_NOISE_RADIUS: f32 = 160.0;
+// how much quieter it gets for each pixel of wall it goes through, 16 pixels take 0.4 off
+const NOISE_WALL_LOSS: f32 = 0.025;
+// how far apart the points are that the walls are looked for at
+const NOISE_WALL_STEP: f32 = 2.0;
+
+pub struct Noise {
+	pub pos: Vec2,
+	pub radius: f32,
+	age: f32,
+}
+
+impl Noise {
+	pub fn new(pos: Vec2, radius: f32) -> Self {
+		Self {pos, radius, age: 0.0}
+	}
+
+	// only heard in the first update after it was made
+	pub fn is_new(&self) -> bool {
+		self.age <= 0.0
+	}
+
+	// how loud it is at pos, nothing at or under 0 is heard
+	pub fn loudness_at(&self, walls: &[MultiCollider<CollBox>], pos: Vec2) -> f32 {
+		let distance = self.pos.distance(pos);
+		if distance >= self.radius {
+			return 0.0;
+		}
+		let loudness = 1.0 - distance / self.radius;
+		if line_of_sight(walls, self.pos, pos) {
+			return loudness;
+		}
+
+		let steps = (distance / NOISE_WALL_STEP).ceil() as usize;
+		let in_wall = (0..steps).filter(|&step| {
+			let point = self.pos.lerp(pos, (step as f32 + 0.5) / steps as f32);
+			MultiCollider::check_intersection_slice(walls, &collbox!(point, Vec2::splat(0.01)))
+		}).count();
+		loudness - in_wall as f32 * distance / steps as f32 * NOISE_WALL_LOSS
+	}
+
+	pub fn heard_at(&self, walls: &[MultiCollider<CollBox>], pos: Vec2) -> bool {
+		self.loudness_at(walls, pos) > 0.0
+	}
+
+	// returns false once the ripple is gone, it takes as long as the trap snapping
+	pub fn update(&mut self, dt: f32) -> bool {
+		self.age += dt;
+		self.age < TRAP_ANIM_TIME
+	}
+
+	// fades out on the way like the flash of a trap
+	pub fn draw(&self) {
+		let time = (self.age / TRAP_ANIM_TIME).min(1.0);
+		let mut color = WHITE;
+		color.a = 1.0 - time * time;
+		draw_circle_lines(self.pos.x, self.pos.y, self.radius * time, 1.0, color);
+	}
+}
@@ -52,5 +52,15 @@ pub const EXIT_COLOR: Color = color_hex!(0xb69440ff);
 pub const BREAKABLE_COLOR: Color = color_hex!(0x409075ff);
 pub const BREAKABLE_COLOR_FADE: Color = color_hex!(0x28745aff);
 
+// switches and gates are tinted by their channel
+pub const CHANNEL_COLORS: [Color; 6] = [
+	color_hex!(0xd8c25aff),
+	color_hex!(0x4f9be0ff),
+	color_hex!(0xd9683cff),
+	color_hex!(0x9a6fd6ff),
+	color_hex!(0x5cc7a3ff),
+	color_hex!(0xd9d9d9ff),
+];
+
 pub const WINDOW_COLOR: Color = color_hex!(0x352f49ff);
 pub const WINDOW_LIGHT: Color = color_hex!(0x7d89b5ff);
\ No newline at end of file
@@ -0,0 +1,91 @@
+// picks the level pack that is layered over the base assets, F3 opens it in game.
+//   up / down or W / S go through the packs, Enter plays the selected one, Escape closes it
+// a pack's Campaign.par can have a title and an author to show here
+
+use macroquad::prelude::*;
+
+use crate::custom_text::*;
+use crate::parser::*;
+use crate::localization::*;
+use crate::vfs;
+
+const MENU_FONT_SIZE: f32 = 0.5;
+const MENU_BACKGROUND: Color = Color::new(0.0, 0.0, 0.0, 0.75);
+
+pub struct PackEntry {
+	// None is the base game, its title comes from the string tables
+	pub pack: Option<String>,
+	pub title: String,
+	pub author: String,
+}
+
+pub enum PackMenuAction {
+	Stay,
+	Close,
+	Choose(Option<String>),
+}
+
+pub struct PackMenu {
+	entries: Vec<PackEntry>,
+	selected: usize,
+}
+
+impl PackMenu {
+	pub async fn new() -> Self {
+		let mut entries = vec![PackEntry {
+			pack: None,
+			title: String::new(),
+			author: String::new(),
+		}];
+
+		for pack in vfs::find_packs() {
+			let campaign_path = format!("{}/{}/Levels/Campaign.par", vfs::PACK_FOLDER, pack);
+			let campaign = StringParser::new(&vfs::load_string(&campaign_path).await.unwrap_or_default());
+			entries.push(PackEntry {
+				title: campaign.get_as_string_literal_or_def("title", &pack).trim().to_string(),
+				author: campaign.get_as_string_literal_or_def("author", "").trim().to_string(),
+				pack: Some(pack),
+			});
+		}
+
+		let active = vfs::get_active_pack();
+		let selected = entries.iter().position(|e| e.pack == active).unwrap_or(0);
+		Self {entries, selected}
+	}
+
+	pub fn update(&mut self) -> PackMenuAction {
+		if is_key_pressed(KeyCode::Down) || is_key_pressed(KeyCode::S) {
+			self.selected = (self.selected + 1) % self.entries.len();
+		}
+		if is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::W) {
+			self.selected = (self.selected + self.entries.len() - 1) % self.entries.len();
+		}
+
+		if is_key_pressed(KeyCode::Enter) {
+			return PackMenuAction::Choose(self.entries[self.selected].pack.clone());
+		}
+		if is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::F3) {
+			return PackMenuAction::Close;
+		}
+		PackMenuAction::Stay
+	}
+
+	pub fn draw(&self, font: &RFont, localization: &Localization, cam_position: Vec2, cam_dim: Vec2, time: f32) {
+		let mut text = format!("{}\n", localization.get_or_key("PackMenu.Title"));
+		for (i, entry) in self.entries.iter().enumerate() {
+			text.push_str(if i == self.selected {"\n> "} else {"\n  "});
+			match entry.pack {
+				Some(_) => text.push_str(&entry.title.replace('{', "{{")),
+				None => text.push_str(&localization.get_or_key("PackMenu.BaseGame")),
+			}
+			if !entry.author.is_empty() {
+				text.push_str(&format!(" - {}", entry.author.replace('{', "{{")));
+			}
+		}
+
+		draw_rectangle(cam_position.x - cam_dim.x, cam_position.y - cam_dim.y, cam_dim.x * 2.0, cam_dim.y * 2.0, MENU_BACKGROUND);
+		let para = font.output_para(&text);
+		let corner = cam_position - cam_dim + Vec2::splat(8.0);
+		font.draw_derived_para(corner, WHITE, MENU_FONT_SIZE, &para, TextAlign::Left, time);
+	}
+}
@@ -45,6 +45,10 @@ impl StringParser {
 		println!("{}", self.names);
 	}
 
+	pub fn get_names(&self) -> Vec<&str> {
+		self.names.as_str().split(' ').filter(|n| !n.is_empty()).collect()
+	}
+
 	pub fn get_string<'a>(&'a self, name: &str) -> Option<&'a String> {
 		let mut idx = 0;
 		for n in self.names.as_str().split(' ') {
@@ -60,6 +64,17 @@ impl StringParser {
 		return None;
 	}
 
+	// returns every value with this name, for files where a name can be used more than once
+	pub fn get_all_strings<'a>(&'a self, name: &str) -> Vec<&'a String> {
+		let mut res = Vec::new();
+		for (idx, n) in self.names.as_str().split(' ').enumerate() {
+			if n == name && idx < self.values.len() {
+				res.push(&self.values[idx]);
+			}
+		}
+		res
+	}
+
 	pub fn get_as_ints(&self, name: &str) -> Vec<i32> {
 		match self.get_string(&name) {
 			Some(s) => {
@@ -183,6 +198,50 @@ impl StringParser {
 	}
 }
 
+// finds where a value is in the text of a file, as (name start, value start, value end)
+fn find_value(s: &str, name: &str) -> Option<(usize, usize, usize)> {
+	let mut start = 0;
+	for segment in s.split(';') {
+		let end = start + segment.len();
+		if let Some(eq) = segment.find('=') {
+			let cleaned_name = remove_whitespace(remove_enclosed(&segment[..eq].replace('\r', "\n"), COMMENT_START, '\n').as_str());
+			if cleaned_name == name {
+				let name_start = segment[..eq].rfind(name).unwrap_or(0);
+				return Some((start + name_start, start + eq + 1, end));
+			}
+		}
+		start = end + 1;
+	}
+	None
+}
+
+// swaps out a value in the text of a file, keeping the comments and everything else
+#[allow(dead_code)]
+pub fn replace_value(s: &str, name: &str, value: &str) -> String {
+	match find_value(s, name) {
+		Some((_, value_start, value_end)) => {
+			format!("{}{}{}", &s[..value_start], value, &s[value_end..])
+		}
+
+		None => {
+			let newline = if s.contains('\r') {"\r\n"} else {"\n"};
+			format!("{}{}{}", s, newline, serialize(name, value).replace('\n', newline))
+		}
+	}
+}
+
+#[allow(dead_code)]
+pub fn remove_value(s: &str, name: &str) -> String {
+	match find_value(s, name) {
+		Some((name_start, _, value_end)) => {
+			let after = (value_end + 1).min(s.len());
+			format!("{}{}", &s[..name_start], &s[after..])
+		}
+
+		None => s.to_string(),
+	}
+}
+
 #[allow(dead_code)]
 pub fn remove_enclosed(s:&str, opening: char, closing: char) -> String {
 	let mut ts: &str = s;
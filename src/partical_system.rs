@@ -5,6 +5,7 @@ use macroquad::rand::*;
 use crate::options::*;
 
 use crate::go_helpers::*;
+use crate::game_world::AssetManager;
 
 const COLOR_FADES: &[Color] = &[WHITE, WHITE, DANGER_COLOR, DANGER_COLOR2, BREAKABLE_COLOR, BREAKABLE_COLOR_FADE, PLAYER_COLOR, PLAYER_TIRED_COLOR];
 
@@ -53,7 +54,7 @@ impl ParticalSystem {
 		}
 	}
 
-	pub fn draw(&self, texture: &Texture2D) {
+	pub fn draw(&self, assets: &AssetManager) {
 		for i in 0..self.particals.len() {
 			let partical = &self.particals[i];
 			let time = partical.time / partical.life_time;
@@ -67,9 +68,10 @@ impl ParticalSystem {
 			let color_idx = partical.color_fade * 2;
 			let color = lerp_color(COLOR_FADES[color_idx as usize], COLOR_FADES[color_idx as usize+1], adjusted_time);
 
+			let (texture, source) = assets.sprite_frame("partical", vec2(anim_frame, partical.renderer as i32 as f32));
 			draw_centered_texture(texture, pos, color, DrawTextureParams{
 				dest_size: Some(Vec2::splat(8.0)),
-				source: Some(Rect::new(anim_frame*8.0, 8.0*(partical.renderer as i32 as f32), 8.0, 8.0)),
+				source: Some(source),
 				..Default::default()
 			})
 		}
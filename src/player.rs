@@ -8,6 +8,9 @@ use macroquad::prelude::*;
 use crate::partical_system;
 use crate::partical_system::*;
 use crate::staticobj::*;
+use crate::game_world::AssetManager;
+use crate::wall_man::WallMan;
+use crate::moving_platform::MovingPlatform;
 
 const PLAYER_SPEED: f32 = 96.0*0.7;
 const PLAYER_SPEED_CHANGE: f32 = PLAYER_SPEED * 6.0;
@@ -30,16 +33,19 @@ const PLAYER_JUMP_SPEED:f32 = 128.0 * 0.75;
 const PLAYER_JUMP_CALCEL:f32 = 128.0 * 0.7/2.0;
 const BOUNCE_SPEED: f32 = 64.0/2.0;
 
-const PLAYER_SRC_SIZE:Vec2 = Vec2::splat(16.0);
-
 const PLAYER_DEST_SIZE:Vec2 = Vec2::new(16.0, 16.0);
 const PLAYER_SPRITE_OFFSET: Vec2 = Vec2::new(0.0, -2.0);
 
-const PLAYER_TRAIL: Rect = Rect{x:128.0, y:0.0, w:8.0, h:8.0};
 const PLAYER_TRAIL_SPEED:f32 = 24.0;
 
 const COYOTE_TIME: f32 = 0.25;
 
+// how far down onto slopes and the floor after them the player is pulled while walking,
+// more than a 45 degree slope drops in a frame
+const SLOPE_SNAP: f32 = 4.0;
+// how far off the top of a moving platform the player can be and still ride it
+const RIDE_TOLERANCE: f32 = 0.5;
+
 const PLAYER_IDLE_ANIMATION: Animation = Animation::new(0, 3, 2.0, true);
 
 const PLAYER_WALK_ANIMATION: Animation = Animation::new(5, 2, 0.5, true);
@@ -58,6 +64,8 @@ pub struct Player {
 	pub cbox: CollBox,
 	motion: Vec2,
 	grounded:bool,
+	// holding down drops through one way platforms
+	dropping: bool,
 	lm_dir: f32,
 
 	anim_timer: Animation,
@@ -73,6 +81,7 @@ impl Player {
 			cbox: collbox!((0.0, 0.0), (4.0, 6.0)),// CollBox::new_f32(0.0, 0.0, 4.0, 6.0),
 			motion: Vec2::ZERO,
 			grounded: false,
+			dropping: false,
 			lm_dir: 1.0,
 			anim_timer: PLAYER_IDLE_ANIMATION,
 			player_state: PlayerState::Normal(0.0),
@@ -86,6 +95,7 @@ impl Player {
 		if input.dir.x != 0.0 {
 			self.lm_dir = input.dir.x;
 		}
+		self.dropping = input.dir.y > 0.0;
 
 		
 		self.player_state = match self.player_state {
@@ -206,26 +216,94 @@ impl Player {
 		}
 	}
 
-	pub fn physics_update_mc (&mut self, walls: &[MultiCollider<CollBox>], breakable: &[CollBox], ghost_blocks: &GhostBlocks, dt:f32) {
-		self.cbox.pos.x += self.motion.x * dt;
-		if MultiCollider::collide_x_slice(&mut self.cbox, &walls) {
+	// the player only rides platforms on foot, while flying it floats off and they just push it around
+	pub fn standing_on(&self, cbox: &CollBox) -> bool {
+		let PlayerState::Normal(_) = self.player_state else {return false;};
+		self.grounded && (self.cbox.pos.x - cbox.pos.x).abs() < self.cbox.half_dim.x + cbox.half_dim.x
+			&& (self.cbox.pos.y + self.cbox.half_dim.y - (cbox.pos.y - cbox.half_dim.y)).abs() < RIDE_TOLERANCE
+	}
+
+	// moves with the platform it was standing on before they moved and out of the way of the rest.
+	// returns true when a platform squashed the player into something solid
+	pub fn move_with_platforms(&mut self, platforms: &[MovingPlatform], riding: Option<usize>, walls: &WallMan<Vec2>, breakable: &[CollBox], ghost_blocks: &GhostBlocks) -> bool {
+		let solid_ghost_blocks = ghost_blocks.solid_blocks();
+		let wall_colliders = walls.wall_colliders.as_slice();
+
+		let mut blocked = false;
+		if let Some(i) = riding {
+			// a wall in the way stops the ride, the platform goes on without the player
+			let motion = platforms[i].motion;
+			let hit = self.sweep_solids(motion, wall_colliders, breakable, solid_ghost_blocks, &[]);
+			self.cbox.pos += motion * hit.map_or(1.0, |hit| hit.time);
+			blocked = hit.is_some();
+		}
+
+		for (i, platform) in platforms.iter().enumerate() {
+			if riding == Some(i) && !blocked {
+				continue;
+			}
+			// x first and then y, so it ends up on the side the platform came from
+			let mut moved = platform.cbox;
+			moved.pos.y -= platform.motion.y;
+			if platform.motion.x != 0.0 && moved.check_intersection(&self.cbox) {
+				self.cbox.pos.x = moved.pos.x + (moved.half_dim.x + self.cbox.half_dim.x) * platform.motion.x.signum();
+			}
+			if platform.motion.y != 0.0 && platform.cbox.check_intersection(&self.cbox) {
+				self.cbox.pos.y = platform.cbox.pos.y + (platform.cbox.half_dim.y + self.cbox.half_dim.y) * platform.motion.y.signum();
+			}
+		}
+
+		MultiCollider::check_intersection_slice(wall_colliders, &self.cbox)
+			|| self.cbox.check_intersection_slice(breakable)
+			|| self.cbox.check_intersection_slice(solid_ghost_blocks)
+			|| platforms.iter().any(|platform| platform.cbox.check_intersection(&self.cbox))
+	}
+
+	// moving platforms and closed gates are solid from every side
+	pub fn physics_update_mc (&mut self, walls: &WallMan<Vec2>, breakable: &[CollBox], ghost_blocks: &GhostBlocks, solids: &[CollBox], dt:f32) {
+		let slopes = walls.slopes.as_slice();
+		let platforms = walls.platform_colliders.as_slice();
+		let walls = walls.wall_colliders.as_slice();
+		let solid_ghost_blocks = ghost_blocks.solid_blocks();
+
+		// moves only up to the first thing in the way, so falling fast can't skip through a floor
+		let hit_x = self.sweep_solids(vec2(self.motion.x * dt, 0.0), walls, breakable, solid_ghost_blocks, solids);
+		self.cbox.pos.x += self.motion.x * dt * hit_x.map_or(1.0, |hit| hit.time);
+		let motion_x = self.motion.x;
+		if MultiCollider::collide_x_slice(&mut self.cbox, walls, motion_x) || hit_x.is_some() {
 			self.motion.x = 0.0;
 		}
 
-		if self.cbox.collide_x_slice(&breakable) {
+		if self.cbox.collide_x_slice(breakable, motion_x) {
 			self.motion.x = 0.0;
 		}
-		let solid_ghost_blocks = ghost_blocks.get_block_effect() == GhostBlockEffect::Collide;
-		if  solid_ghost_blocks{
-			if (self.cbox.collide_x_slice(ghost_blocks.get_block_slice())) {
-				self.motion.x = 0.0;
-			}
+		if self.cbox.collide_x_slice(solid_ghost_blocks, motion_x) {
+			self.motion.x = 0.0;
+		}
+		if CollSlope::collide_x_slice(&mut self.cbox, slopes, motion_x) || self.cbox.collide_x_slice(solids, motion_x) {
+			self.motion.x = 0.0;
 		}
 
-		self.cbox.pos.y += self.motion.y * dt;
-		if self.grounded && self.motion.y >= 0.0 {self.cbox.pos.y += 0.01;}
+		let prev_y = self.cbox.pos.y;
+		let hit_y = self.sweep_solids(vec2(0.0, self.motion.y * dt), walls, breakable, solid_ghost_blocks, solids);
+		self.cbox.pos.y += self.motion.y * dt * hit_y.map_or(1.0, |hit| hit.time);
+		if self.grounded && self.motion.y >= 0.0 {
+			// stays on the ground when walking off the bottom of a slope onto the floor a little lower
+			let drop = self.sweep_solids(vec2(0.0, SLOPE_SNAP), walls, breakable, solid_ghost_blocks, solids).map_or(0.0, |hit| hit.time * SLOPE_SNAP);
+			self.cbox.pos.y += drop + 0.01;
+		}
 
-		if MultiCollider::collide_y_slice(&mut self.cbox, &walls) || self.cbox.collide_y_slice(&breakable) || if solid_ghost_blocks {self.cbox.collide_y_slice(ghost_blocks.get_block_slice())} else {false} {
+		let motion_y = self.motion.y;
+		let hit_solid = MultiCollider::collide_y_slice(&mut self.cbox, walls, motion_y) || self.cbox.collide_y_slice(breakable, motion_y)
+			|| self.cbox.collide_y_slice(solid_ghost_blocks, motion_y) || self.cbox.collide_y_slice(solids, motion_y);
+
+		let on_platform = !self.dropping && self.motion.y >= 0.0
+			&& MultiCollider::collide_y_one_way_slice(&mut self.cbox, platforms, prev_y);
+		// keeps the player on slopes when walking down them instead of falling off a little every frame
+		let snap = if self.grounded && self.motion.y >= 0.0 {SLOPE_SNAP} else {0.0};
+		let on_slope = CollSlope::collide_y_slice(&mut self.cbox, slopes, snap);
+
+		if hit_solid || hit_y.is_some() || on_platform || on_slope {
 			self.grounded = self.motion.y >= 0.0;
 			self.motion.y = 0.0;
 		}else {
@@ -233,16 +311,16 @@ impl Player {
 		}
 	}
 
-	pub fn check_ghost_block_kill(&mut self, ghost_blocks: &GhostBlocks) -> bool {
-		if ghost_blocks.get_block_effect() == GhostBlockEffect::Kill {
-			self.cbox.check_intersection_slice(&ghost_blocks.get_block_slice())
-		}else {
-			false
-		}
+	fn sweep_solids(&self, motion: Vec2, walls: &[MultiCollider<CollBox>], breakable: &[CollBox], ghost_blocks: &[CollBox], solids: &[CollBox]) -> Option<SweepHit> {
+		let mut hit = MultiCollider::sweep_box_slice(walls, &self.cbox, motion);
+		hit = SweepHit::first(hit, self.cbox.sweep_slice(motion, breakable));
+		hit = SweepHit::first(hit, self.cbox.sweep_slice(motion, solids));
+		hit = SweepHit::first(hit, self.cbox.sweep_slice(motion, ghost_blocks));
+		hit
 	}
 
 
-	pub fn draw(&self, texture:&Texture2D) {
+	pub fn draw(&self, assets: &AssetManager) {
 		match self.player_state {
 			PlayerState::Dead => {}
 
@@ -252,23 +330,29 @@ impl Player {
 
 				// draw tail
 				if let PlayerState::Flying(trail_loc) = self.player_state {
+					let (texture, source) = assets.sprite("player_trail");
 					params = DrawTextureParams::default();
-					params.source = Some(PLAYER_TRAIL);
+					params.source = Some(source);
 					params.dest_size = Some(Vec2::splat(8.0));
 
-					draw_centered_texture(&texture, trail_loc, color, params);
+					draw_centered_texture(texture, trail_loc, color, params);
 
 					//draw_texture_ex(&texture, draw_loc.x, draw_loc.y, color, params);
 				}
 
 				// draw body
 				let draw_loc = self.cbox.pos + PLAYER_SPRITE_OFFSET - PLAYER_DEST_SIZE*0.5;
+				let (texture, mut frame) = assets.sprite_frame("player", vec2(self.anim_timer.get_frame_idx() as f32, 0.0));
+				if !assets.sprite_has_gap("player") {
+					// inset a little so the frames next to it don't bleed in
+					frame = Rect::new(frame.x+0.01, frame.y+0.01, frame.w-0.02, frame.h-0.02);
+				}
 				params = DrawTextureParams::default();
-				params.source = Some(Rect::new(0.01+PLAYER_SRC_SIZE.x * self.anim_timer.get_frame_idx() as f32, 0.01, PLAYER_SRC_SIZE.x-0.02, PLAYER_SRC_SIZE.y-0.02));
+				params.source = Some(frame);
 				params.dest_size = Some(PLAYER_DEST_SIZE);
 				params.flip_x = self.lm_dir < 0.0;
 
-				draw_centered_texture(&texture, self.cbox.pos+PLAYER_SPRITE_OFFSET, color, params);
+				draw_centered_texture(texture, self.cbox.pos+PLAYER_SPRITE_OFFSET, color, params);
 			}
 		}
 		
@@ -334,4 +418,133 @@ fn get_player_input() -> PlayerInput {
 		jump: is_key_down(KeyCode::Space),
 		fly: is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::K),
 	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TILE: f32 = 16.0;
+	const DT: f32 = 1.0 / 60.0;
+
+	// builds the walls the way load_level does for the glyphs the terrain tests use
+	fn terrain(layout: &str) -> WallMan<Vec2> {
+		let mut walls = WallMan::new(generate_multi_col_grid(Vec2::splat(-8.0), Vec2::splat(64.0), uvec2(4, 2)), Vec2::ZERO);
+		for (y, row) in layout.lines().enumerate() {
+			for (x, glyph) in row.chars().enumerate() {
+				let pos = vec2(x as f32, y as f32) * TILE;
+				let tile = collbox!(pos, Vec2::splat(TILE * 0.5));
+				match glyph {
+					'#' => walls.add_wall(tile),
+					'=' => walls.add_platform(collbox!(pos - vec2(0.0, (TILE - 4.0) * 0.5), (TILE * 0.5, 2.0))),
+					'<' => walls.add_slope(CollSlope::new(tile, 0.0, TILE)),
+					'>' => walls.add_slope(CollSlope::new(tile, TILE, 0.0)),
+					'{' => walls.add_slope(CollSlope::new(tile, 0.0, TILE * 0.5)),
+					'[' => walls.add_slope(CollSlope::new(tile, TILE * 0.5, TILE)),
+					']' => walls.add_slope(CollSlope::new(tile, TILE, TILE * 0.5)),
+					'}' => walls.add_slope(CollSlope::new(tile, TILE * 0.5, 0.0)),
+					_ => {}
+				}
+			}
+		}
+		walls.build_colliders();
+		walls
+	}
+
+	// standing with its feet at floor_y
+	fn player_at(x: f32, floor_y: f32) -> Player {
+		let mut player = Player::new();
+		player.cbox.pos = vec2(x, floor_y - player.cbox.half_dim.y);
+		player.grounded = true;
+		player
+	}
+
+	fn bottom(player: &Player) -> f32 {
+		player.cbox.pos.y + player.cbox.half_dim.y
+	}
+
+	// one frame of walking in dir with gravity, without reading the keyboard
+	fn step(player: &mut Player, walls: &WallMan<Vec2>, dir: f32) {
+		player.motion.x = dir * PLAYER_SPEED;
+		player.motion.y = (player.motion.y + PLAYER_GRAVITY * DT).min(PLAYER_FALL_SPEED);
+		player.physics_update_mc(walls, &[], &GhostBlocks::new(Vec2::splat(TILE * 0.5)), &[], DT);
+	}
+
+	// walks from one end of the hill to the other and back, it has to stay on the ground the
+	// whole way and never sink into a slope
+	fn walk_over(layout: &str, floor_y: f32, top_y: f32) {
+		let walls = terrain(layout);
+		let mut player = player_at(TILE, floor_y);
+		for (dir, end_x) in [(1.0, 10.0 * TILE), (-1.0, TILE)] {
+			let mut highest = floor_y;
+			for frame in 0..240 {
+				step(&mut player, &walls, dir);
+				assert!(player.grounded, "{} fell off in frame {} at {}", layout, frame, player.cbox.pos);
+				for slope in &walls.slopes {
+					if (player.cbox.pos.x - slope.cbox.pos.x).abs() < slope.cbox.half_dim.x {
+						assert!(bottom(&player) <= slope.rest_y(&player.cbox) + 0.02, "{} sank into a slope at {}", layout, player.cbox.pos);
+					}
+				}
+				highest = highest.min(bottom(&player));
+				if (player.cbox.pos.x - end_x) * dir >= 0.0 {
+					break;
+				}
+			}
+			assert!((player.cbox.pos.x - end_x) * dir >= 0.0, "{} got stuck at {}", layout, player.cbox.pos);
+			assert!((highest - top_y).abs() < 0.05, "{} only got up to {}", layout, highest);
+			assert!((bottom(&player) - floor_y).abs() < 0.05, "{} ended at {}", layout, player.cbox.pos);
+		}
+	}
+
+	#[test]
+	fn walks_over_steep_slopes() {
+		walk_over("\n\n...<##>....\n############", 2.5 * TILE, 1.5 * TILE);
+	}
+
+	#[test]
+	fn walks_over_gentle_slopes() {
+		walk_over("\n\n..{[##]}...\n############", 2.5 * TILE, 1.5 * TILE);
+	}
+
+	#[test]
+	fn one_way_platforms() {
+		let walls = terrain("\n\n...===....\n\n##########");
+		let (platform_y, floor_y) = (1.5 * TILE, 3.5 * TILE);
+
+		// lands on it from above
+		let mut player = player_at(4.0 * TILE, 0.0);
+		player.grounded = false;
+		for _ in 0..60 {
+			step(&mut player, &walls, 0.0);
+		}
+		assert!(player.grounded);
+		assert!((bottom(&player) - platform_y).abs() < 0.05, "landed at {}", player.cbox.pos);
+
+		// jumps up through it from the floor and lands on top
+		let mut player = player_at(4.0 * TILE, floor_y);
+		player.motion.y = -PLAYER_JUMP_SPEED - PLAYER_GRAVITY * DT;
+		for frame in 0..90 {
+			let (prev_y, rising) = (player.cbox.pos.y, player.motion.y + PLAYER_GRAVITY * DT < 0.0);
+			step(&mut player, &walls, 0.0);
+			if rising {
+				assert!(player.cbox.pos.y < prev_y, "stopped under the platform in frame {}", frame);
+			}
+		}
+		assert!(player.grounded);
+		assert!((bottom(&player) - platform_y).abs() < 0.05, "jumped to {}", player.cbox.pos);
+
+		// stays on it until down is held
+		let mut player = player_at(4.0 * TILE, platform_y);
+		for _ in 0..30 {
+			step(&mut player, &walls, 0.0);
+		}
+		assert!(player.grounded);
+		assert!((bottom(&player) - platform_y).abs() < 0.05, "stood at {}", player.cbox.pos);
+		player.dropping = true;
+		for _ in 0..60 {
+			step(&mut player, &walls, 0.0);
+		}
+		assert!(player.grounded);
+		assert!((bottom(&player) - floor_y).abs() < 0.05, "dropped to {}", player.cbox.pos);
+	}
 }
\ No newline at end of file
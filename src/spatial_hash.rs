@@ -0,0 +1,388 @@
+// a uniform grid for finding the things near a box without checking every one of them.
+// everything is stored by a key together with its box, update only moves a key between
+// cells when it crosses into different ones so things that move every frame stay cheap.
+// the game keys things by their index in the Vec they live in, swap_remove keeps that working
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use macroquad::math::*;
+
+use crate::collision::*;
+
+#[derive(Clone, Copy)]
+struct Entry {
+	cbox: CollBox,
+	min: IVec2,
+	max: IVec2,
+}
+
+pub struct SpatialHash<K> {
+	cell_size: f32,
+	cells: HashMap<IVec2, Vec<K>>,
+	entries: HashMap<K, Entry>,
+}
+
+#[allow(dead_code)]
+impl<K> SpatialHash<K> where K: Copy + Eq + Hash {
+	pub fn new(cell_size: f32) -> Self {
+		Self {
+			cell_size,
+			cells: HashMap::new(),
+			entries: HashMap::new(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	pub fn clear(&mut self) {
+		self.cells.clear();
+		self.entries.clear();
+	}
+
+	pub fn get(&self, key: K) -> Option<&CollBox> {
+		self.entries.get(&key).map(|e| &e.cbox)
+	}
+
+	// the same as update, a key that is already there is moved
+	pub fn insert(&mut self, key: K, cbox: CollBox) {
+		self.update(key, cbox);
+	}
+
+	pub fn update(&mut self, key: K, cbox: CollBox) {
+		let (min, max) = self.cell_range(&cbox);
+		if let Some(entry) = self.entries.get_mut(&key) {
+			entry.cbox = cbox;
+			if entry.min == min && entry.max == max {
+				return;
+			}
+			let old = *entry;
+			self.unlink(key, old.min, old.max);
+		}
+		self.link(key, min, max);
+		self.entries.insert(key, Entry {cbox, min, max});
+	}
+
+	pub fn remove(&mut self, key: K) -> Option<CollBox> {
+		let entry = self.entries.remove(&key)?;
+		self.unlink(key, entry.min, entry.max);
+		Some(entry.cbox)
+	}
+
+	// gives what was stored under from to to, anything already under to is dropped
+	pub fn rekey(&mut self, from: K, to: K) {
+		if from == to {
+			return;
+		}
+		self.remove(to);
+		if let Some(cbox) = self.remove(from) {
+			self.insert(to, cbox);
+		}
+	}
+
+	// calls f once for every key whose box overlaps cbox
+	pub fn query<F>(&self, cbox: &CollBox, mut f: F) where F: FnMut(K, &CollBox) {
+		let (min, max) = self.cell_range(cbox);
+		for y in min.y..=max.y {
+			for x in min.x..=max.x {
+				let Some(keys) = self.cells.get(&ivec2(x, y)) else {continue;};
+				for key in keys {
+					let entry = &self.entries[key];
+					// a box in more than one cell is only looked at in the first cell both ranges share
+					if ivec2(x, y) != min.max(entry.min) {
+						continue;
+					}
+					if entry.cbox.check_intersection(cbox) {
+						f(*key, &entry.cbox);
+					}
+				}
+			}
+		}
+	}
+
+	pub fn query_keys(&self, cbox: &CollBox) -> Vec<K> {
+		let mut res = Vec::new();
+		self.query(cbox, |key, _| res.push(key));
+		res
+	}
+
+	// f isn't called any more once it returned true
+	pub fn any<F>(&self, cbox: &CollBox, f: F) -> bool where F: Fn(K, &CollBox) -> bool {
+		let mut found = false;
+		self.query(cbox, |key, other| found = found || f(key, other));
+		found
+	}
+
+	fn cell_range(&self, cbox: &CollBox) -> (IVec2, IVec2) {
+		let min = ((cbox.pos - cbox.half_dim) / self.cell_size).floor().as_ivec2();
+		let max = ((cbox.pos + cbox.half_dim) / self.cell_size).floor().as_ivec2();
+		(min, max)
+	}
+
+	fn link(&mut self, key: K, min: IVec2, max: IVec2) {
+		for y in min.y..=max.y {
+			for x in min.x..=max.x {
+				self.cells.entry(ivec2(x, y)).or_default().push(key);
+			}
+		}
+	}
+
+	fn unlink(&mut self, key: K, min: IVec2, max: IVec2) {
+		for y in min.y..=max.y {
+			for x in min.x..=max.x {
+				let cell = ivec2(x, y);
+				let Some(keys) = self.cells.get_mut(&cell) else {continue;};
+				if let Some(i) = keys.iter().position(|k| *k == key) {
+					keys.swap_remove(i);
+				}
+				if keys.is_empty() {
+					self.cells.remove(&cell);
+				}
+			}
+		}
+	}
+}
+
+#[allow(dead_code)]
+impl SpatialHash<usize> {
+	// for when the Vec the indices point into did swap_remove(index), last is the index the
+	// moved value used to have
+	pub fn swap_remove(&mut self, index: usize, last: usize) {
+		self.remove(index);
+		if index != last {
+			self.rekey(last, index);
+		}
+	}
+
+	// puts every value in under its index, only the ones that moved cells cost anything
+	pub fn update_all<T, F>(&mut self, vals: &[T], f: F) where F: Fn(&T) -> CollBox {
+		for (i, val) in vals.iter().enumerate() {
+			self.update(i, f(val));
+		}
+		if self.entries.len() > vals.len() {
+			let stale: Vec<usize> = self.entries.keys().copied().filter(|&i| i >= vals.len()).collect();
+			for i in stale {
+				self.remove(i);
+			}
+		}
+	}
+}
+
+// swap removes every index in indices, they can be in any order and more than once
+pub fn swap_remove_all<T>(vals: &mut Vec<T>, grid: &mut SpatialHash<usize>, mut indices: Vec<usize>) {
+	indices.sort_unstable();
+	indices.dedup();
+	for i in indices.into_iter().rev() {
+		vals.swap_remove(i);
+		grid.swap_remove(i, vals.len());
+	}
+}
+
+// like run_and_remove but swap removes, so the grid only has to fix the one moved index
+pub fn run_and_swap_remove<T, F>(vals: &mut Vec<T>, grid: &mut SpatialHash<usize>, mut f: F) where F: FnMut(&mut T) -> bool {
+	for i in (0..vals.len()).rev() {
+		if f(&mut vals[i]) {
+			vals.swap_remove(i);
+			grid.swap_remove(i, vals.len());
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::go_helpers::TestRng as Rng;
+
+	const CASES: usize = 400;
+	const STEPS: usize = 60;
+	const CELL_SIZE: f32 = 32.0;
+	// goes below 0 so the negative cells get used too
+	const AREA: f32 = 256.0;
+
+	trait GridCases {
+		fn snapped(&mut self, low: f32, high: f32) -> f32;
+		fn cbox(&mut self) -> CollBox;
+		fn nudged(&mut self, cbox: &CollBox) -> CollBox;
+	}
+
+	impl GridCases for Rng {
+		// on cell edges now and then, boxes that end right on one are the easy ones to get wrong
+		fn snapped(&mut self, low: f32, high: f32) -> f32 {
+			if self.next().is_multiple_of(4) {
+				(self.range(low, high) / CELL_SIZE).round() * CELL_SIZE
+			} else {
+				(self.range(low, high) * 4.0).round() / 4.0
+			}
+		}
+
+		// mostly small, sometimes bigger than a few cells
+		fn cbox(&mut self) -> CollBox {
+			let max_half = if self.next().is_multiple_of(5) {CELL_SIZE * 3.0} else {CELL_SIZE * 0.5};
+			collbox!(vec2(self.snapped(-AREA, AREA), self.snapped(-AREA, AREA)), vec2(self.range(0.25, max_half), self.range(0.25, max_half)))
+		}
+
+		// a little way off, so it often stays in the same cells
+		fn nudged(&mut self, cbox: &CollBox) -> CollBox {
+			collbox!(cbox.pos + vec2(self.range(-8.0, 8.0), self.range(-8.0, 8.0)), cbox.half_dim)
+		}
+	}
+
+	fn same_box(a: &CollBox, b: &CollBox) -> bool {
+		a.pos == b.pos && a.half_dim == b.half_dim
+	}
+
+	// every index the grid gives for cbox, in the order it gave them
+	fn queried(grid: &SpatialHash<usize>, cbox: &CollBox) -> Vec<usize> {
+		let mut res = Vec::new();
+		grid.query(cbox, |i, other| {
+			res.push(i);
+			assert!(same_box(other, grid.get(i).unwrap()));
+		});
+		res
+	}
+
+	// the grid has to know exactly what checking every box would find
+	fn check(grid: &SpatialHash<usize>, vals: &[CollBox], rng: &mut Rng, case: usize) {
+		assert_eq!(grid.len(), vals.len(), "case {}", case);
+		for (i, cbox) in vals.iter().enumerate() {
+			assert!(grid.get(i).is_some_and(|stored| same_box(stored, cbox)), "case {}: index {} is out of date", case, i);
+		}
+		assert!(grid.get(vals.len()).is_none(), "case {}: a removed index is still there", case);
+
+		for _ in 0..8 {
+			let area = rng.cbox();
+			let mut found = queried(grid, &area);
+			let count = found.len();
+			found.sort_unstable();
+			found.dedup();
+			assert_eq!(found.len(), count, "case {}: a box was given more than once", case);
+
+			let expected: Vec<usize> = (0..vals.len()).filter(|&i| vals[i].check_intersection(&area)).collect();
+			assert_eq!(found, expected, "case {}", case);
+			assert_eq!(grid.any(&area, |_, _| true), !expected.is_empty(), "case {}", case);
+		}
+	}
+
+	#[test]
+	fn queries_match_brute_force() {
+		let mut rng = Rng(0x9e3779b97f4a7c15);
+		for case in 0..CASES {
+			let mut vals: Vec<CollBox> = Vec::new();
+			let mut grid = SpatialHash::new(CELL_SIZE);
+			for _ in 0..STEPS {
+				match rng.below(6) {
+					0 | 1 => {
+						let cbox = rng.cbox();
+						grid.insert(vals.len(), cbox);
+						vals.push(cbox);
+					}
+					2 if !vals.is_empty() => {
+						let i = rng.below(vals.len());
+						vals[i] = if rng.next().is_multiple_of(2) {rng.nudged(&vals[i])} else {rng.cbox()};
+						grid.update(i, vals[i]);
+					}
+					3 if !vals.is_empty() => {
+						let i = rng.below(vals.len());
+						vals.swap_remove(i);
+						grid.swap_remove(i, vals.len());
+					}
+					4 if !vals.is_empty() => {
+						// out of order and with repeats, like the broken walls of a frame
+						let indices = (0..rng.below(4) + 1).map(|_| rng.below(vals.len())).collect();
+						swap_remove_all(&mut vals, &mut grid, indices);
+					}
+					5 => {
+						let odds = rng.below(4) as u64 + 2;
+						run_and_swap_remove(&mut vals, &mut grid, |_| rng.next().is_multiple_of(odds));
+					}
+					_ => {}
+				}
+				check(&grid, &vals, &mut rng, case);
+			}
+		}
+	}
+
+	#[test]
+	fn update_all_moves_everything_and_drops_stale_indices() {
+		let mut rng = Rng(0x2545f4914f6cdd1d);
+		for case in 0..CASES {
+			let mut vals: Vec<CollBox> = (0..rng.below(40)).map(|_| rng.cbox()).collect();
+			let mut grid = SpatialHash::new(CELL_SIZE);
+			grid.update_all(&vals, |cbox| *cbox);
+			check(&grid, &vals, &mut rng, case);
+
+			for _ in 0..8 {
+				for cbox in vals.iter_mut() {
+					if rng.next().is_multiple_of(2) {
+						*cbox = rng.nudged(cbox);
+					}
+				}
+				// the Vec shrinks or grows behind the grid's back
+				let len = rng.below(vals.len() + 8);
+				vals.truncate(len);
+				while vals.len() < len {
+					vals.push(rng.cbox());
+				}
+				grid.update_all(&vals, |cbox| *cbox);
+				check(&grid, &vals, &mut rng, case);
+			}
+		}
+	}
+
+	#[test]
+	fn rekey_to_itself_keeps_it() {
+		let mut grid = SpatialHash::new(CELL_SIZE);
+		let cbox = collbox!(vec2(40.0, 40.0), vec2(4.0, 4.0));
+		grid.insert(1, cbox);
+		grid.rekey(1, 1);
+		assert!(grid.get(1).is_some_and(|stored| same_box(stored, &cbox)));
+		assert_eq!(grid.query_keys(&cbox), vec![1]);
+	}
+
+	#[test]
+	fn rekey_replaces_what_was_there() {
+		let mut rng = Rng(0xda942042e4dd58b5);
+		for case in 0..CASES {
+			let mut stored: HashMap<u32, CollBox> = HashMap::new();
+			let mut grid = SpatialHash::new(CELL_SIZE);
+			for _ in 0..STEPS {
+				let key = rng.below(12) as u32;
+				if rng.next().is_multiple_of(2) {
+					let cbox = rng.cbox();
+					grid.insert(key, cbox);
+					stored.insert(key, cbox);
+				} else {
+					let to = rng.below(12) as u32;
+					grid.rekey(key, to);
+					if key != to {
+						stored.remove(&to);
+						if let Some(cbox) = stored.remove(&key) {
+							stored.insert(to, cbox);
+						}
+					}
+				}
+
+				assert_eq!(grid.len(), stored.len(), "case {}", case);
+				for key in 0..12 {
+					match (grid.get(key), stored.get(&key)) {
+						(Some(a), Some(b)) => assert!(same_box(a, b), "case {}", case),
+						(None, None) => {}
+						_ => panic!("case {}: key {} is wrong", case, key),
+					}
+				}
+				let area = rng.cbox();
+				let mut found = grid.query_keys(&area);
+				found.sort_unstable();
+				let mut expected: Vec<u32> = stored.iter().filter(|(_, cbox)| cbox.check_intersection(&area)).map(|(k, _)| *k).collect();
+				expected.sort_unstable();
+				assert_eq!(found, expected, "case {}", case);
+			}
+		}
+	}
+}
@@ -1,14 +1,20 @@
+use std::collections::HashMap;
 use std::default;
 
 use macroquad::prelude::*;
 
 use crate::collision::*;
+use crate::spatial_hash::*;
 
 use crate::options::*;
 
 use crate::go_helpers::*;
+use crate::game_world::AssetManager;
+use crate::channels::*;
 
 pub const TRAP_ANIM_TIME: f32 = 0.5;
+// how long a gate takes to open or close
+const GATE_TIME: f32 = 0.25;
 
 const ORIENT_DIR: Vec2 = vec2(0.7, 1.4);
 
@@ -29,51 +35,83 @@ impl Trigger {
 	pub fn update(objs: &mut [Self], dt: f32) {
 		for i in 0..objs.len() {
 			match objs[i].trigger_type {
-				TriggerType::Trap(time) => {
-					objs[i].trigger_type = TriggerType::Trap(0.0f32.max(time-dt))
+				TriggerType::Trap(time, channel) => {
+					objs[i].trigger_type = TriggerType::Trap(0.0f32.max(time-dt), channel)
 				}
 				_ => {}
 			}
 		}
 	}
 
-	pub fn coll_check <FCB>(objs: &mut [Self], other: &CollBox, mut callback: FCB) where FCB: FnMut(&Trigger) -> bool {
-		for i in 0..objs.len() {
-			if objs[i].coll.check_intersection(&other) {
-				if callback(&objs[i]) {
-					match objs[i].trigger_type {
-						TriggerType::Trap(time) => {
-							if time < 0.01 {
-								objs[i].trigger_type = TriggerType::Trap(TRAP_ANIM_TIME);
-							}
-						}
-						_=>{}
+	// called when something touches it, traps snap shut. returns true when one did
+	pub fn activate(&mut self) -> bool {
+		match self.trigger_type {
+			TriggerType::Trap(time, channel) => {
+				if time < 0.01 {
+					self.trigger_type = TriggerType::Trap(TRAP_ANIM_TIME, channel);
+					return true;
+				}
+			}
+			_=>{}
+		}
+		false
+	}
+
+	// sends the channel events for the switches. touched are the triggers anything was on this frame,
+	// player_touched the ones the player was on, only the player pulls levers and picks up keys
+	pub fn update_switches(objs: &mut [Self], touched: &[usize], player_touched: &[usize], channels: &mut Channels) {
+		for (i, obj) in objs.iter_mut().enumerate() {
+			let pressed = touched.contains(&i);
+			let player_on = player_touched.contains(&i);
+			match obj.trigger_type {
+				TriggerType::PressurePlate(channel, was_pressed) if pressed != was_pressed => {
+					channels.send(if pressed {ChannelEvent::Pressed(channel)} else {ChannelEvent::Released(channel)});
+					obj.trigger_type = TriggerType::PressurePlate(channel, pressed);
+				}
+				// flips when the player walks into it, not every frame they stand there
+				TriggerType::Lever(channel, on, held) => {
+					let flip = player_on && !held;
+					if flip {
+						channels.send(ChannelEvent::Toggled(channel));
 					}
+					obj.trigger_type = TriggerType::Lever(channel, on != flip, player_on);
+				}
+				TriggerType::Key(channel, false) if player_on => {
+					channels.send(ChannelEvent::Unlocked(channel));
+					obj.trigger_type = TriggerType::Key(channel, true);
 				}
+				_ => {}
 			}
 		}
 	}
 
-	pub fn draw (&self, texture: &Texture2D) {
+	pub fn draw (&self, assets: &AssetManager) {
 		let flip = self.coll.pos.dot(ORIENT_DIR) % 2.0 < 1.2;
 		match self.trigger_type {
-			TriggerType::FlyBox => draw_centered_texture(&texture, self.coll.pos, FB_COLOR, DrawTextureParams{
-				source: Some(Rect::new(0.0, 64.0, 48.0, 48.0)),
-				dest_size: Some(Vec2::splat(48.0)),
-				flip_x: flip,
-				..DrawTextureParams::default()
-			}),
+			TriggerType::FlyBox => {
+				let (texture, source) = assets.sprite("fly_box");
+				draw_centered_texture(texture, self.coll.pos, FB_COLOR, DrawTextureParams{
+					source: Some(source),
+					dest_size: Some(Vec2::splat(48.0)),
+					flip_x: flip,
+					..DrawTextureParams::default()
+				})
+			}
 
-			TriggerType::Spikes => draw_centered_texture(&texture, self.coll.pos, DANGER_COLOR, DrawTextureParams{
-				source: Some(Rect::new(32.0, 112.0, 16.0, 16.0)),
-				dest_size: Some(Vec2::splat(16.0)),
-				flip_x: flip,
-				..DrawTextureParams::default()
-			}),
+			TriggerType::Spikes => {
+				let (texture, source) = assets.sprite("spike");
+				draw_centered_texture(texture, self.coll.pos, DANGER_COLOR, DrawTextureParams{
+					source: Some(source),
+					dest_size: Some(Vec2::splat(16.0)),
+					flip_x: flip,
+					..DrawTextureParams::default()
+				})
+			}
 
-			TriggerType::Trap(time) => {
-				draw_centered_texture(&texture, self.coll.pos, WHITE, DrawTextureParams{
-					source: Some(Rect::new(if TRAP_ANIM_TIME * 0.5 > time {48.0} else {48.0+16.0}, 112.0, 16.0, 16.0)),
+			TriggerType::Trap(time, _) => {
+				let (texture, source) = assets.sprite(if TRAP_ANIM_TIME * 0.5 > time {"trap_closed"} else {"trap_open"});
+				draw_centered_texture(texture, self.coll.pos, WHITE, DrawTextureParams{
+					source: Some(source),
 					dest_size: Some(Vec2::splat(16.0)),
 					flip_x: flip,
 					..DrawTextureParams::default()
@@ -83,13 +121,45 @@ impl Trigger {
 				let mut color = WHITE;
 				color.a = 1.0-time*time;
 				
-				draw_centered_texture(&texture, self.coll.pos, color, DrawTextureParams {
-					source: Some(Rect::new(80.0, 112.0, 16.0, 16.0)),
+				let (texture, source) = assets.sprite("trap_flash");
+				draw_centered_texture(texture, self.coll.pos, color, DrawTextureParams {
+					source: Some(source),
 					dest_size: Some(Vec2::splat(96.0*time)),
 					..DrawTextureParams::default()
 				});
 			},
 
+			TriggerType::PressurePlate(channel, pressed) => {
+				let (texture, source) = assets.sprite("plate");
+				let height = if pressed {2.0} else {4.0};
+				let bottom = self.coll.pos.y + self.coll.half_dim.y;
+				draw_centered_texture(texture, vec2(self.coll.pos.x, bottom - height * 0.5), channel_color(channel), DrawTextureParams{
+					source: Some(source),
+					dest_size: Some(vec2(16.0, height)),
+					..DrawTextureParams::default()
+				});
+			}
+
+			TriggerType::Lever(channel, on, _) => {
+				let (texture, source) = assets.sprite("lever");
+				draw_centered_texture(texture, self.coll.pos, channel_color(channel), DrawTextureParams{
+					source: Some(source),
+					dest_size: Some(Vec2::splat(16.0)),
+					flip_x: on,
+					..DrawTextureParams::default()
+				});
+			}
+
+			TriggerType::Key(channel, false) => {
+				let (texture, source) = assets.sprite("key");
+				draw_centered_texture(texture, self.coll.pos, channel_color(channel), DrawTextureParams{
+					source: Some(source),
+					dest_size: Some(Vec2::splat(16.0)),
+					flip_x: flip,
+					..DrawTextureParams::default()
+				});
+			}
+
 			_ => {}
 		}
 	}
@@ -97,72 +167,293 @@ impl Trigger {
 
 pub enum TriggerType {
 	FlyBox,
-	Trap (f32),
+	// the time left on the snap and the channel of the ghost blocks it drives, 0 for none
+	Trap (f32, u32),
 	Spikes,
+	// the channel and if something is on it
+	PressurePlate (u32, bool),
+	// the channel, which way it's pulled and if the player is touching it
+	Lever (u32, bool, bool),
+	// the channel and if it was picked up
+	Key (u32, bool),
+}
+
+pub fn channel_color(channel: u32) -> Color {
+	CHANNEL_COLORS[channel as usize % CHANNEL_COLORS.len()]
+}
+
+// blocks the way until its channel turns on, gates that start open close instead
+pub struct Gate {
+	pub cbox: CollBox,
+	pub channel: u32,
+	starts_open: bool,
+	open: bool,
+	// 0 is shut and 1 all the way open
+	anim: f32,
+}
+
+impl Gate {
+	pub fn new(cbox: CollBox, channel: u32, starts_open: bool) -> Self {
+		Self {cbox, channel, starts_open, open: starts_open, anim: if starts_open {1.0} else {0.0}}
+	}
+
+	// a gate with something in it waits for it to leave before it closes
+	pub fn update(&mut self, channels: &Channels, blocked: bool, dt: f32) {
+		let open = channels.is_on(self.channel) != self.starts_open;
+		if open || !blocked {
+			self.open = open;
+		}
+		let dir = if self.open {1.0} else {-1.0};
+		self.anim = (self.anim + dir * dt / GATE_TIME).clamp(0.0, 1.0);
+	}
+
+	pub fn is_solid(&self) -> bool {
+		!self.open
+	}
+
+	// slides up into the top of its tile
+	pub fn draw(&self, assets: &AssetManager) {
+		if self.anim >= 1.0 {
+			return;
+		}
+		let (texture, mut source) = assets.sprite("gate");
+		let height = self.cbox.half_dim.y * 2.0 * (1.0 - self.anim);
+		source.y += source.h * self.anim;
+		source.h *= 1.0 - self.anim;
+		let top = self.cbox.pos.y - self.cbox.half_dim.y;
+		draw_centered_texture(texture, vec2(self.cbox.pos.x, top + height * 0.5), channel_color(self.channel), DrawTextureParams{
+			source: Some(source),
+			dest_size: Some(vec2(self.cbox.half_dim.x * 2.0, height)),
+			..DrawTextureParams::default()
+		});
+	}
 }
 
 const BLOCK_FADE: f32 = 0.3;
 const BLOCK_KILL_TIME: f32 = 0.1;
 const BLOCK_TIME: f32 = 1.2;
+const GHOST_GRID_SIZE: f32 = 64.0;
+
+// how the ghost blocks without a channel are split up, ghostGroups in the level file
+#[derive(Clone, Copy, PartialEq)]
+pub enum GhostGrouping {
+	// all of them fade when any trap without a channel snaps
+	Together,
+	// blocks that touch are a group and each trap drives the group closest to it
+	Nearest,
+}
+
+impl GhostGrouping {
+	pub fn from_name(name: &str) -> Self {
+		match name.trim().to_lowercase().as_str() {
+			"nearest" => GhostGrouping::Nearest,
+			_ => GhostGrouping::Together,
+		}
+	}
+}
+
+// blocks that fade in and out together
+struct GhostGroup {
+	// blocks put on a channel also fade while it's on
+	channel: Option<u32>,
+	// the indices of the traps that make it fade
+	traps: Vec<usize>,
+	hit_timer: f32,
+	anim_time: f32,
+	anim_dir: f32,
+}
+
+impl GhostGroup {
+	fn new(channel: Option<u32>) -> Self {
+		Self {channel, traps: Vec::new(), hit_timer: 0.0, anim_time: 0.2, anim_dir: -1.0}
+	}
+
+	fn update(&mut self, triggered: bool, dt: f32) {
+		self.anim_dir = if triggered {
+			self.hit_timer = BLOCK_TIME;
+			1.0
+		} else if self.hit_timer > 0.0 {
+			1.0
+		} else {
+			-1.0
+		};
+		self.hit_timer = 0.0f32.max(self.hit_timer - dt);
+
+		self.anim_time = (self.anim_time + dt*self.anim_dir).clamp(0.0, BLOCK_FADE);
+	}
+
+	fn effect(&self) -> GhostBlockEffect {
+		if self.anim_time <= 0.0 {return GhostBlockEffect::Collide;}
+		if self.anim_dir < 0.0 && self.anim_time < BLOCK_KILL_TIME {return GhostBlockEffect::Kill;}
+		GhostBlockEffect::PassThrough
+	}
+}
 
 pub struct GhostBlocks {
 	blocks: Vec<CollBox>,
+	// the channel each block was put on, 0 for none
+	channels: Vec<u32>,
+	// the group each block is in, set by build_groups
+	block_groups: Vec<usize>,
+	groups: Vec<GhostGroup>,
+	grid: SpatialHash<usize>,
 	pub dim: Vec2,
-	hit_timer: f32,
-	anim_time: f32,
-	anim_dir: f32
+	// the blocks that are solid right now
+	solid: Vec<CollBox>,
 }
 
 impl GhostBlocks {
 	pub fn new (block_size: Vec2) -> Self {
-		Self{blocks: Vec::new(), dim: block_size, hit_timer: 0.0, anim_time: 0.2, anim_dir: -1.0}
+		Self{blocks: Vec::new(), channels: Vec::new(), block_groups: Vec::new(), groups: Vec::new(), grid: SpatialHash::new(GHOST_GRID_SIZE), dim: block_size, solid: Vec::new()}
 	}
 
-	pub fn add (&mut self, pos: Vec2) {
+	pub fn add (&mut self, pos: Vec2, channel: u32) {
+		self.grid.insert(self.blocks.len(), collbox!(pos, self.dim));
 		self.blocks.push(collbox!(pos, self.dim));
+		self.channels.push(channel);
 	}
 
 	pub fn clear(&mut self) {
 		self.blocks.clear();
+		self.channels.clear();
+		self.block_groups.clear();
+		self.groups.clear();
+		self.grid.clear();
+		self.solid.clear();
 	}
 
-	pub fn update (&mut self, nm_triggered: bool, dt: f32) {
-		self.anim_dir = if nm_triggered {
-			self.hit_timer = BLOCK_TIME;
-			1.0
-		} else if self.hit_timer > 0.0 {
-			1.0
-		} else {
-			-1.0
-		};
-		self.hit_timer = 0.0f32.max(self.hit_timer - dt);
+	// called once all the blocks are in. traps are (index, position, channel) for every trap
+	pub fn build_groups(&mut self, traps: &[(usize, Vec2, u32)], grouping: GhostGrouping) {
+		self.groups.clear();
+		self.block_groups = vec![usize::MAX; self.blocks.len()];
 
-		self.anim_time = (self.anim_time + dt*self.anim_dir).max(0.0).min(BLOCK_FADE);
+		// one group for every channel
+		let mut channel_groups: HashMap<u32, usize> = HashMap::new();
+		for i in 0..self.blocks.len() {
+			let channel = self.channels[i];
+			if channel == 0 {
+				continue;
+			}
+			let group = *channel_groups.entry(channel).or_insert_with(|| {
+				self.groups.push(GhostGroup::new(Some(channel)));
+				self.groups.len() - 1
+			});
+			self.block_groups[i] = group;
+		}
+		for &(trap, _, channel) in traps {
+			if let Some(&group) = channel_groups.get(&channel) {
+				self.groups[group].traps.push(trap);
+			}
+		}
+
+		// and the rest
+		let free_traps = traps.iter().filter(|(_, _, channel)| *channel == 0);
+		match grouping {
+			GhostGrouping::Together => {
+				if self.channels.contains(&0) {
+					let mut group = GhostGroup::new(None);
+					group.traps = free_traps.map(|(trap, _, _)| *trap).collect();
+					for i in 0..self.blocks.len() {
+						if self.channels[i] == 0 {
+							self.block_groups[i] = self.groups.len();
+						}
+					}
+					self.groups.push(group);
+				}
+			}
+
+			GhostGrouping::Nearest => {
+				for start in 0..self.blocks.len() {
+					if self.block_groups[start] != usize::MAX {
+						continue;
+					}
+					// fills out to every block without a channel that touches one already in
+					let group = self.groups.len();
+					self.groups.push(GhostGroup::new(None));
+					self.block_groups[start] = group;
+					let mut open = vec![start];
+					while let Some(i) = open.pop() {
+						for other in self.grid.query_keys(&collbox!(self.blocks[i].pos, self.dim + Vec2::ONE)) {
+							if self.block_groups[other] == usize::MAX && self.channels[other] == 0 {
+								self.block_groups[other] = group;
+								open.push(other);
+							}
+						}
+					}
+				}
+				for &(trap, pos, _) in free_traps {
+					let nearest = (0..self.blocks.len()).filter(|&i| self.channels[i] == 0)
+						.min_by(|&a, &b| self.blocks[a].pos.distance_squared(pos).total_cmp(&self.blocks[b].pos.distance_squared(pos)));
+					if let Some(i) = nearest {
+						self.groups[self.block_groups[i]].traps.push(trap);
+					}
+				}
+			}
+		}
+		self.update_solid();
+	}
+
+	// sprung are the traps something was in this frame
+	pub fn update (&mut self, sprung: &[usize], channels: &Channels, dt: f32) {
+		for group in self.groups.iter_mut() {
+			let triggered = group.channel.is_some_and(|channel| channels.is_on(channel))
+				|| group.traps.iter().any(|trap| sprung.contains(trap));
+			group.update(triggered, dt);
+		}
+		self.update_solid();
+	}
+
+	fn update_solid(&mut self) {
+		self.solid = (0..self.blocks.len()).filter(|&i| self.block_effect(i) == GhostBlockEffect::Collide).map(|i| self.blocks[i]).collect();
 	}
 
-	pub fn draw(&self, texture: &Texture2D) {
+	pub fn draw(&self, assets: &AssetManager) {
+		let (texture, source) = assets.sprite("ghost_block");
 		for i in 0..self.blocks.len() {
-			let mut anim_time = self.anim_time / BLOCK_FADE;
+			let group = &self.groups[self.block_groups[i]];
+			let mut anim_time = group.anim_time / BLOCK_FADE;
 			anim_time += anim_time * anim_time;
 			anim_time *= 0.5;
-			draw_centered_texture(&texture, self.blocks[i].pos, lerp_color(Color::new(0.5, 0.5, 0.5, 1.0), Color::new(0.4, 0.4, 0.4, 0.25), anim_time), DrawTextureParams{
+			let mut color = lerp_color(Color::new(0.5, 0.5, 0.5, 1.0), Color::new(0.4, 0.4, 0.4, 0.25), anim_time);
+			if let Some(channel) = group.channel {
+				color = lerp_color(color, Color {a: color.a, ..channel_color(channel)}, 0.3);
+			}
+			draw_centered_texture(texture, self.blocks[i].pos, color, DrawTextureParams{
 				dest_size: Some(Vec2::splat(16.0)),
-				source: Some(Rect::new(80.0, 96.0, 16.0, 16.0)),
+				source: Some(source),
 				..DrawTextureParams::default()
 			});
 		}
 	} 
 
-	pub fn get_block_effect (&self) -> GhostBlockEffect{
-		if self.anim_time <= 0.0 {return GhostBlockEffect::Collide;}
-		if self.anim_dir < 0.0 && self.anim_time < BLOCK_KILL_TIME {return GhostBlockEffect::Kill;}
-		GhostBlockEffect::PassThrough
+	pub fn block_effect (&self, index: usize) -> GhostBlockEffect {
+		self.groups[self.block_groups[index]].effect()
 	}
 
 	pub fn get_block_slice<'a> (&'a self) -> &'a [CollBox] {
 		&self.blocks
 	}
 
+	pub fn solid_blocks(&self) -> &[CollBox] {
+		&self.solid
+	}
+
+	// only against the solid blocks
+	pub fn sweep_circle(&self, circle: &CollCircle, motion: Vec2) -> Option<SweepHit> {
+		let mut res = None;
+		self.grid.query(&swept_bounds(&circle.to_box(), motion), |i, block| {
+			if self.block_effect(i) == GhostBlockEffect::Collide {
+				res = SweepHit::first(res, circle.sweep_box(motion, block));
+			}
+		});
+		res
+	}
+
+	// the indices of the blocks overlapping cbox
+	pub fn query_near(&self, cbox: &CollBox) -> Vec<usize> {
+		self.grid.query_keys(cbox)
+	}
+
 
 }
 
@@ -171,4 +462,143 @@ pub enum GhostBlockEffect {
 	Collide,
 	Kill,
 	PassThrough
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn switch(trigger_type: TriggerType) -> Trigger {
+		Trigger::new(collbox!(Vec2::ZERO, Vec2::splat(8.0)), trigger_type)
+	}
+
+	// one frame of contacts, gives what the channels did
+	fn frame(objs: &mut [Trigger], touched: &[usize], player_touched: &[usize], channels: &mut Channels) -> Vec<(u32, bool)> {
+		Trigger::update_switches(objs, touched, player_touched, channels);
+		channels.dispatch()
+	}
+
+	#[test]
+	fn levers_flip_when_the_player_walks_into_them() {
+		let mut channels = Channels::new();
+		let mut objs = [switch(TriggerType::Lever(1, false, false))];
+
+		assert_eq!(frame(&mut objs, &[0], &[0], &mut channels), vec![(1, true)]);
+		// standing on it doesn't keep flipping it
+		for _ in 0..3 {
+			assert!(frame(&mut objs, &[0], &[0], &mut channels).is_empty());
+		}
+		assert!(matches!(objs[0].trigger_type, TriggerType::Lever(1, true, true)));
+
+		assert!(frame(&mut objs, &[], &[], &mut channels).is_empty());
+		assert_eq!(frame(&mut objs, &[0], &[0], &mut channels), vec![(1, false)]);
+
+		// enemies don't pull levers
+		assert!(frame(&mut objs, &[], &[], &mut channels).is_empty());
+		assert!(frame(&mut objs, &[0], &[], &mut channels).is_empty());
+		assert!(matches!(objs[0].trigger_type, TriggerType::Lever(1, false, false)));
+	}
+
+	#[test]
+	fn keys_are_only_picked_up_once() {
+		let mut channels = Channels::new();
+		let mut objs = [switch(TriggerType::Key(2, false))];
+
+		assert!(frame(&mut objs, &[0], &[], &mut channels).is_empty());
+		assert_eq!(frame(&mut objs, &[0], &[0], &mut channels), vec![(2, true)]);
+		assert!(matches!(objs[0].trigger_type, TriggerType::Key(2, true)));
+
+		for _ in 0..3 {
+			assert!(frame(&mut objs, &[], &[], &mut channels).is_empty());
+			assert!(frame(&mut objs, &[0], &[0], &mut channels).is_empty());
+		}
+		assert!(channels.is_on(2));
+	}
+
+	#[test]
+	fn plates_are_held_by_anything_on_them() {
+		let mut channels = Channels::new();
+		let mut objs = [switch(TriggerType::PressurePlate(3, false)), switch(TriggerType::PressurePlate(3, false))];
+
+		assert_eq!(frame(&mut objs, &[0], &[], &mut channels), vec![(3, true)]);
+		assert!(frame(&mut objs, &[0, 1], &[1], &mut channels).is_empty());
+		assert!(frame(&mut objs, &[1], &[1], &mut channels).is_empty());
+		assert_eq!(frame(&mut objs, &[], &[], &mut channels), vec![(3, false)]);
+	}
+	// tiles of the blocks on the grid the levels use
+	fn ghost_blocks(tiles: &[(f32, f32, u32)]) -> GhostBlocks {
+		let mut blocks = GhostBlocks::new(Vec2::splat(8.0));
+		for &(x, y, channel) in tiles {
+			blocks.add(vec2(x, y) * 16.0, channel);
+		}
+		blocks
+	}
+
+	// everything solid again, then one frame of sprung traps. gives which blocks aren't solid
+	fn spring(blocks: &mut GhostBlocks, sprung: &[usize], channels: &Channels) -> Vec<bool> {
+		// the first update runs out the hold, the second fades back in
+		for _ in 0..2 {
+			blocks.update(&[], &Channels::new(), BLOCK_TIME + BLOCK_FADE);
+		}
+		blocks.update(sprung, channels, BLOCK_KILL_TIME * 0.5);
+		(0..blocks.get_block_slice().len()).map(|i| blocks.block_effect(i) != GhostBlockEffect::Collide).collect()
+	}
+
+	#[test]
+	fn touching_blocks_are_grouped_with_the_nearest_trap() {
+		// an L, a block touching its corner and one on its own
+		let mut blocks = ghost_blocks(&[(0.0, 0.0, 0), (1.0, 0.0, 0), (1.0, 1.0, 0), (2.0, 2.0, 0), (6.0, 0.0, 0)]);
+		blocks.build_groups(&[(10, vec2(6.0, 3.0) * 16.0, 0), (11, vec2(-2.0, 0.0) * 16.0, 0)], GhostGrouping::Nearest);
+		let groups = &blocks.block_groups;
+		assert!(groups[0] == groups[1] && groups[1] == groups[2] && groups[2] == groups[3]);
+		assert_ne!(groups[3], groups[4]);
+
+		let channels = Channels::new();
+		assert_eq!(spring(&mut blocks, &[10], &channels), [false, false, false, false, true]);
+		assert_eq!(spring(&mut blocks, &[11], &channels), [true, true, true, true, false]);
+		assert_eq!(spring(&mut blocks, &[], &channels), [false; 5]);
+	}
+
+	#[test]
+	fn a_group_without_a_trap_near_it_stays_solid() {
+		let mut blocks = ghost_blocks(&[(0.0, 0.0, 0), (4.0, 0.0, 0), (8.0, 0.0, 0)]);
+		// both traps are closest to the middle block
+		blocks.build_groups(&[(0, vec2(4.0, 2.0) * 16.0, 0), (1, vec2(5.0, -1.0) * 16.0, 0)], GhostGrouping::Nearest);
+		let channels = Channels::new();
+		assert_eq!(spring(&mut blocks, &[0], &channels), [false, true, false]);
+		assert_eq!(spring(&mut blocks, &[1], &channels), [false, true, false]);
+	}
+
+	#[test]
+	fn blocks_on_a_channel_are_their_own_group() {
+		// the channel blocks touch the others but don't join them
+		let tiles = [(0.0, 0.0, 0), (1.0, 0.0, 3), (2.0, 0.0, 3), (3.0, 0.0, 0), (9.0, 0.0, 3)];
+		let traps = [(0, vec2(1.0, 1.0) * 16.0, 0), (1, vec2(20.0, 0.0) * 16.0, 3)];
+		for grouping in [GhostGrouping::Nearest, GhostGrouping::Together] {
+			let mut blocks = ghost_blocks(&tiles);
+			blocks.build_groups(&traps, grouping);
+			let groups = &blocks.block_groups;
+			assert!(groups[1] == groups[2] && groups[2] == groups[4]);
+			assert!(groups[0] != groups[1] && groups[3] != groups[1]);
+
+			// a trap without a channel goes to the nearest block without one
+			let mut channels = Channels::new();
+			let expected_free = [true, false, false, grouping == GhostGrouping::Together, false];
+			assert_eq!(spring(&mut blocks, &[0], &channels), expected_free);
+			assert_eq!(spring(&mut blocks, &[1], &channels), [false, true, true, false, true]);
+
+			channels.send(ChannelEvent::Toggled(3));
+			channels.dispatch();
+			assert_eq!(spring(&mut blocks, &[], &channels), [false, true, true, false, true]);
+		}
+	}
+
+	#[test]
+	fn together_is_one_group_for_every_free_block() {
+		let mut blocks = ghost_blocks(&[(0.0, 0.0, 0), (5.0, 0.0, 0), (10.0, 5.0, 0)]);
+		blocks.build_groups(&[(0, vec2(0.0, 1.0) * 16.0, 0), (1, vec2(10.0, 4.0) * 16.0, 0)], GhostGrouping::Together);
+		let channels = Channels::new();
+		assert_eq!(spring(&mut blocks, &[0], &channels), [true; 3]);
+		assert_eq!(spring(&mut blocks, &[1], &channels), [true; 3]);
+	}
 }
\ No newline at end of file
@@ -0,0 +1,644 @@
+// imports maps made in the Tiled editor (.tmx and .json) as level blueprints.
+// tile layers have to use csv encoding, which is the default in Tiled.
+//
+// a gid turns into a glyph by the "glyph" property on its tile in the tileset, or else by
+// its place in the glyphs string, so the first tile is glyphs[0].
+// objects use their "glyph" property, then a one letter class, then their gid.
+// infinite maps are stored in chunks and aren't read, untick "Infinite" in the map properties
+
+use std::collections::HashMap;
+
+use macroquad::math::*;
+
+use crate::game_world::LevTile;
+
+const GID_FLAGS: u32 = 0xf0000000;
+
+pub struct TiledLevel {
+	pub tiles: Vec<LevTile>,
+	pub size: UVec2,
+}
+
+struct TileMapper {
+	glyphs: Vec<char>,
+	tile_glyphs: HashMap<u32, char>,
+}
+
+impl TileMapper {
+	fn new(glyphs: &str) -> Self {
+		Self {
+			glyphs: glyphs.chars().collect(),
+			tile_glyphs: HashMap::new(),
+		}
+	}
+
+	fn get_glyph(&self, gid: u32) -> Option<char> {
+		let gid = gid & !GID_FLAGS;
+		if gid == 0 {
+			return None;
+		}
+		match self.tile_glyphs.get(&gid) {
+			Some(c) => Some(*c),
+			None => self.glyphs.get(gid as usize - 1).copied().filter(|c| *c != ' '),
+		}
+	}
+}
+
+struct TiledObject {
+	pos: Vec2,
+	size: Vec2,
+	gid: Option<u32>,
+	point: bool,
+	class: String,
+	props: Vec<(String, String)>,
+}
+
+impl TiledObject {
+	// tiled measures from the corner of the map, levels measure from the middle of the first tile
+	fn get_center(&self, tile_dim: Vec2) -> Vec2 {
+		let center = if self.point {
+			self.pos
+		} else if self.gid.is_some() {
+			// tile objects are anchored at their bottom left
+			self.pos + vec2(self.size.x, -self.size.y) * 0.5
+		} else {
+			self.pos + self.size * 0.5
+		};
+
+		center - tile_dim * 0.5
+	}
+
+	fn into_tile(self, mapper: &TileMapper, tile_dim: Vec2, scale: Vec2) -> Option<LevTile> {
+		let glyph = match self.props.iter().find(|(name, _)| name == "glyph") {
+			Some((_, value)) => value.chars().next(),
+			None => {
+				let mut class = self.class.chars();
+				match (class.next(), class.next()) {
+					(Some(c), None) => Some(c),
+					_ => self.gid.and_then(|gid| mapper.get_glyph(gid)),
+				}
+			}
+		}?;
+
+		Some(LevTile {
+			pos: self.get_center(tile_dim) * scale,
+			ty: glyph,
+			props: self.props,
+		})
+	}
+}
+
+fn add_tile_layer(tiles: &mut Vec<LevTile>, data: &[u32], width: u32, mapper: &TileMapper, grid_size: f32) {
+	if width == 0 {return;}
+	for (i, gid) in data.iter().enumerate() {
+		if let Some(glyph) = mapper.get_glyph(*gid) {
+			let tile_pos = uvec2(i as u32 % width, i as u32 / width);
+			tiles.push(LevTile {
+				pos: tile_pos.as_vec2() * grid_size,
+				ty: glyph,
+				props: Vec::new(),
+			});
+		}
+	}
+}
+
+fn parse_csv(data: &str) -> Vec<u32> {
+	data.split(',').filter_map(|v| v.trim().parse().ok()).collect()
+}
+
+//
+// tmx
+//
+
+struct XmlTag<'a> {
+	name: &'a str,
+	attrs: HashMap<&'a str, String>,
+	closing: bool,
+	self_closing: bool,
+	// the text between this tag and the next one
+	text: &'a str,
+}
+
+impl XmlTag<'_> {
+	fn get(&self, name: &str) -> Option<&str> {
+		self.attrs.get(name).map(|s| s.as_str())
+	}
+
+	fn get_f32(&self, name: &str) -> f32 {
+		self.get(name).and_then(|v| v.parse().ok()).unwrap_or(0.0)
+	}
+
+	fn get_u32(&self, name: &str) -> u32 {
+		self.get(name).and_then(|v| v.parse().ok()).unwrap_or(0)
+	}
+}
+
+fn unescape_xml(s: &str) -> String {
+	s.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+fn read_xml_tags(src: &str) -> Vec<XmlTag<'_>> {
+	let mut tags = Vec::new();
+	let mut rest = src;
+
+	while let Some(start) = rest.find('<') {
+		let after = &rest[start + 1..];
+		let end = match after.find('>') {
+			Some(e) => e,
+			None => break,
+		};
+		let inner = &after[..end];
+		rest = &after[end + 1..];
+
+		// skips <?xml ?> and comments
+		if inner.starts_with('?') || inner.starts_with('!') {
+			continue;
+		}
+
+		let closing = inner.starts_with('/');
+		let self_closing = inner.ends_with('/');
+		let inner = inner.trim_start_matches('/').trim_end_matches('/');
+		let (name, mut attr_src) = inner.split_once(char::is_whitespace).unwrap_or((inner, ""));
+
+		let mut attrs = HashMap::new();
+		while let Some((key, after_key)) = attr_src.split_once('=') {
+			let after_key = after_key.trim_start();
+			let quote = match after_key.chars().next() {
+				Some(q) if q == '"' || q == '\'' => q,
+				_ => break,
+			};
+			let (value, after_value) = after_key[1..].split_once(quote).unwrap_or((&after_key[1..], ""));
+			attrs.insert(key.trim(), unescape_xml(value));
+			attr_src = after_value;
+		}
+
+		let text = &rest[..rest.find('<').unwrap_or(rest.len())];
+		tags.push(XmlTag {name, attrs, closing, self_closing, text});
+	}
+
+	tags
+}
+
+pub fn read_tmx(src: &str, glyphs: &str, grid_size: f32) -> Result<TiledLevel, String> {
+	let tags = read_xml_tags(src);
+	let map = tags.iter().find(|t| t.name == "map").ok_or("not a tmx map")?;
+	if map.get("infinite") == Some("1") {
+		return Err("infinite tiled maps can't be read".to_string());
+	}
+	let size = uvec2(map.get_u32("width"), map.get_u32("height"));
+	let tile_dim = vec2(map.get_f32("tilewidth"), map.get_f32("tileheight")).max(Vec2::ONE);
+	let scale = Vec2::splat(grid_size) / tile_dim;
+
+	let mut mapper = TileMapper::new(glyphs);
+	let mut tiles = Vec::new();
+
+	let mut first_gid = 0;
+	let mut tile_id = None;
+	let mut layer_width = size.x;
+	let mut object: Option<TiledObject> = None;
+
+	for tag in tags.iter() {
+		match (tag.name, tag.closing) {
+			("tileset", false) => {
+				if tag.get("source").is_some() {
+					println!("Tiled: external tilesets are not read, use the glyphs string or embed the tileset");
+				}
+				first_gid = tag.get_u32("firstgid");
+			}
+
+			("tile", false) => tile_id = if tag.self_closing {None} else {Some(tag.get_u32("id"))},
+			("tile", true) => tile_id = None,
+
+			("layer", false) => layer_width = tag.get_u32("width"),
+
+			("data", false) => {
+				match tag.get("encoding") {
+					Some("csv") => add_tile_layer(&mut tiles, &parse_csv(tag.text), layer_width, &mapper, grid_size),
+					_ => return Err("tmx layers need to use csv encoding".to_string()),
+				}
+			}
+			("chunk", false) => return Err("infinite tiled maps can't be read".to_string()),
+
+			("object", false) => {
+				let new_object = TiledObject {
+					pos: vec2(tag.get_f32("x"), tag.get_f32("y")),
+					size: vec2(tag.get_f32("width"), tag.get_f32("height")),
+					gid: tag.get("gid").and_then(|g| g.parse().ok()),
+					point: false,
+					class: tag.get("class").or(tag.get("type")).unwrap_or("").to_string(),
+					props: match tag.get("name") {
+						Some(name) if !name.is_empty() => vec![("name".to_string(), name.to_string())],
+						_ => Vec::new(),
+					},
+				};
+				if tag.self_closing {
+					tiles.extend(new_object.into_tile(&mapper, tile_dim, scale));
+				} else {
+					object = Some(new_object);
+				}
+			}
+			("object", true) => {
+				if let Some(obj) = object.take() {
+					tiles.extend(obj.into_tile(&mapper, tile_dim, scale));
+				}
+			}
+
+			("point", false) => {
+				if let Some(obj) = &mut object {obj.point = true;}
+			}
+
+			("property", false) => {
+				let name = tag.get("name").unwrap_or("").to_string();
+				let value = tag.get("value").unwrap_or(tag.text).to_string();
+				if let Some(obj) = &mut object {
+					obj.props.push((name, value));
+				} else if let (Some(id), true) = (tile_id, name == "glyph") {
+					if let Some(c) = value.chars().next() {
+						mapper.tile_glyphs.insert(first_gid + id, c);
+					}
+				}
+			}
+
+			_ => {}
+		}
+	}
+
+	Ok(TiledLevel {tiles, size})
+}
+
+//
+// json
+//
+
+pub enum JsonValue {
+	Null,
+	Bool(bool),
+	Number(f64),
+	Str(String),
+	Array(Vec<JsonValue>),
+	Object(Vec<(String, JsonValue)>),
+}
+
+const JSON_NULL: JsonValue = JsonValue::Null;
+
+impl JsonValue {
+	pub fn get(&self, key: &str) -> &JsonValue {
+		match self {
+			JsonValue::Object(fields) => fields.iter().find(|(k, _)| k == key).map_or(&JSON_NULL, |(_, v)| v),
+			_ => &JSON_NULL,
+		}
+	}
+
+	pub fn as_f32(&self) -> f32 {
+		match self {
+			JsonValue::Number(n) => *n as f32,
+			_ => 0.0,
+		}
+	}
+
+	pub fn as_u32(&self) -> u32 {
+		match self {
+			JsonValue::Number(n) => *n as u32,
+			_ => 0,
+		}
+	}
+
+	pub fn as_str(&self) -> &str {
+		match self {
+			JsonValue::Str(s) => s,
+			_ => "",
+		}
+	}
+
+	pub fn as_slice(&self) -> &[JsonValue] {
+		match self {
+			JsonValue::Array(a) => a,
+			_ => &[],
+		}
+	}
+
+	pub fn is_null(&self) -> bool {
+		matches!(self, JsonValue::Null)
+	}
+
+	// the value the way it would be written in a level file
+	pub fn to_par_string(&self) -> String {
+		match self {
+			JsonValue::Null => String::new(),
+			JsonValue::Bool(b) => (*b as i32).to_string(),
+			JsonValue::Number(n) => n.to_string(),
+			JsonValue::Str(s) => s.clone(),
+			JsonValue::Array(_) | JsonValue::Object(_) => String::new(),
+		}
+	}
+}
+
+struct JsonReader<'a> {
+	chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl JsonReader<'_> {
+	fn skip_whitespace(&mut self) {
+		while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+			self.chars.next();
+		}
+	}
+
+	fn expect(&mut self, c: char) -> Result<(), String> {
+		self.skip_whitespace();
+		match self.chars.next() {
+			Some(n) if n == c => Ok(()),
+			n => Err(format!("json: expected '{}' but found {:?}", c, n)),
+		}
+	}
+
+	fn read_value(&mut self) -> Result<JsonValue, String> {
+		self.skip_whitespace();
+		match self.chars.peek().copied() {
+			Some('{') => {
+				self.chars.next();
+				let mut fields = Vec::new();
+				self.skip_whitespace();
+				if self.chars.peek() == Some(&'}') {
+					self.chars.next();
+					return Ok(JsonValue::Object(fields));
+				}
+				loop {
+					self.skip_whitespace();
+					let key = self.read_string()?;
+					self.expect(':')?;
+					fields.push((key, self.read_value()?));
+					self.skip_whitespace();
+					match self.chars.next() {
+						Some(',') => continue,
+						Some('}') => return Ok(JsonValue::Object(fields)),
+						n => return Err(format!("json: unexpected {:?} in object", n)),
+					}
+				}
+			}
+
+			Some('[') => {
+				self.chars.next();
+				let mut values = Vec::new();
+				self.skip_whitespace();
+				if self.chars.peek() == Some(&']') {
+					self.chars.next();
+					return Ok(JsonValue::Array(values));
+				}
+				loop {
+					values.push(self.read_value()?);
+					self.skip_whitespace();
+					match self.chars.next() {
+						Some(',') => continue,
+						Some(']') => return Ok(JsonValue::Array(values)),
+						n => return Err(format!("json: unexpected {:?} in array", n)),
+					}
+				}
+			}
+
+			Some('"') => Ok(JsonValue::Str(self.read_string()?)),
+
+			Some(_) => {
+				let mut word = String::new();
+				while let Some(c) = self.chars.peek().copied() {
+					if c == ',' || c == ']' || c == '}' || c.is_whitespace() {break;}
+					word.push(c);
+					self.chars.next();
+				}
+				match word.as_str() {
+					"null" => Ok(JsonValue::Null),
+					"true" => Ok(JsonValue::Bool(true)),
+					"false" => Ok(JsonValue::Bool(false)),
+					_ => word.parse().map(JsonValue::Number).map_err(|_| format!("json: can't read {}", word)),
+				}
+			}
+
+			None => Err("json: unexpected end of file".to_string()),
+		}
+	}
+
+	fn read_string(&mut self) -> Result<String, String> {
+		self.expect('"')?;
+		let mut res = String::new();
+		loop {
+			match self.chars.next() {
+				Some('"') => return Ok(res),
+				Some('\\') => match self.chars.next() {
+					Some('n') => res.push('\n'),
+					Some('t') => res.push('\t'),
+					Some('r') => res.push('\r'),
+					Some('u') => {
+						let hex: String = self.chars.by_ref().take(4).collect();
+						res.push(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32).unwrap_or('?'));
+					}
+					Some(c) => res.push(c),
+					None => break,
+				},
+				Some(c) => res.push(c),
+				None => break,
+			}
+		}
+		Err("json: unterminated string".to_string())
+	}
+}
+
+pub fn parse_json(src: &str) -> Result<JsonValue, String> {
+	JsonReader {chars: src.chars().peekable()}.read_value()
+}
+
+// layers can be inside groups
+fn flatten_json_layers<'a>(layers: &'a [JsonValue], res: &mut Vec<&'a JsonValue>) {
+	for layer in layers {
+		if layer.get("type").as_str() == "group" {
+			flatten_json_layers(layer.get("layers").as_slice(), res);
+		} else {
+			res.push(layer);
+		}
+	}
+}
+
+fn read_json_properties(value: &JsonValue) -> Vec<(String, String)> {
+	value.as_slice().iter()
+		.map(|p| (p.get("name").as_str().to_string(), p.get("value").to_par_string()))
+		.collect()
+}
+
+pub fn read_tiled_json(src: &str, glyphs: &str, grid_size: f32) -> Result<TiledLevel, String> {
+	let map = parse_json(src)?;
+	if map.get("type").as_str() != "map" {
+		return Err("not a tiled json map".to_string());
+	}
+	if matches!(map.get("infinite"), JsonValue::Bool(true)) {
+		return Err("infinite tiled maps can't be read".to_string());
+	}
+
+	let size = uvec2(map.get("width").as_u32(), map.get("height").as_u32());
+	let tile_dim = vec2(map.get("tilewidth").as_f32(), map.get("tileheight").as_f32()).max(Vec2::ONE);
+	let scale = Vec2::splat(grid_size) / tile_dim;
+
+	let mut mapper = TileMapper::new(glyphs);
+	for tileset in map.get("tilesets").as_slice() {
+		if !tileset.get("source").is_null() {
+			println!("Tiled: external tilesets are not read, use the glyphs string or embed the tileset");
+		}
+		let first_gid = tileset.get("firstgid").as_u32();
+		for tile in tileset.get("tiles").as_slice() {
+			let props = read_json_properties(tile.get("properties"));
+			if let Some((_, glyph)) = props.iter().find(|(name, _)| name == "glyph") {
+				if let Some(c) = glyph.chars().next() {
+					mapper.tile_glyphs.insert(first_gid + tile.get("id").as_u32(), c);
+				}
+			}
+		}
+	}
+
+	let mut tiles = Vec::new();
+	let mut layers = Vec::new();
+	flatten_json_layers(map.get("layers").as_slice(), &mut layers);
+	for layer in layers {
+		match layer.get("type").as_str() {
+			"tilelayer" => {
+				if !layer.get("encoding").is_null() && layer.get("encoding").as_str() != "csv" {
+					return Err("json tile layers need to use csv encoding".to_string());
+				}
+				if !layer.get("chunks").is_null() {
+					return Err("infinite tiled maps can't be read".to_string());
+				}
+				let data: Vec<u32> = layer.get("data").as_slice().iter().map(|v| v.as_u32()).collect();
+				add_tile_layer(&mut tiles, &data, layer.get("width").as_u32(), &mapper, grid_size);
+			}
+
+			"objectgroup" => {
+				for obj in layer.get("objects").as_slice() {
+					let mut props = read_json_properties(obj.get("properties"));
+					if !obj.get("name").as_str().is_empty() {
+						props.insert(0, ("name".to_string(), obj.get("name").as_str().to_string()));
+					}
+					let class = if obj.get("class").is_null() {obj.get("type")} else {obj.get("class")};
+
+					let tiled_obj = TiledObject {
+						pos: vec2(obj.get("x").as_f32(), obj.get("y").as_f32()),
+						size: vec2(obj.get("width").as_f32(), obj.get("height").as_f32()),
+						gid: if obj.get("gid").is_null() {None} else {Some(obj.get("gid").as_u32())},
+						point: matches!(obj.get("point"), JsonValue::Bool(true)),
+						class: class.as_str().to_string(),
+						props,
+					};
+					tiles.extend(tiled_obj.into_tile(&mapper, tile_dim, scale));
+				}
+			}
+
+			_ => {}
+		}
+	}
+
+	Ok(TiledLevel {tiles, size})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const GLYPHS: &str = "#/";
+	// twice the size of the tiles in the maps, so the scaling gets checked too
+	const GRID_SIZE: f32 = 32.0;
+
+	// the third tile gets its glyph from the tileset, the last gid has the horizontal flip flag set
+	const TMX_MAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="3" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="16" tilecount="3" columns="3">
+  <tile id="2">
+   <properties>
+    <property name="glyph" value="^"/>
+   </properties>
+  </tile>
+ </tileset>
+ <layer id="1" name="walls" width="3" height="2">
+  <data encoding="csv">
+1,0,3,
+0,2,2147483649
+</data>
+ </layer>
+ <objectgroup id="2" name="things">
+  <object id="1" name="door" x="16" y="0" width="16" height="16">
+   <properties>
+    <property name="glyph" value="X"/>
+    <property name="channel" type="int" value="2"/>
+   </properties>
+  </object>
+  <object id="2" type="E" x="40" y="24">
+   <point/>
+  </object>
+  <object id="3" gid="2" x="0" y="32" width="16" height="16"/>
+ </objectgroup>
+</map>
+"#;
+
+	const JSON_MAP: &str = r#"{ "type":"map", "width":3, "height":2, "tilewidth":16, "tileheight":16, "infinite":false,
+		"tilesets":[{ "firstgid":1, "name":"tiles",
+			"tiles":[{ "id":2, "properties":[{ "name":"glyph", "type":"string", "value":"^" }] }] }],
+		"layers":[
+			{ "type":"group", "name":"level", "layers":[
+				{ "type":"tilelayer", "name":"walls", "width":3, "height":2, "data":[1, 0, 3, 0, 2, 2147483649] }
+			] },
+			{ "type":"objectgroup", "name":"things", "objects":[
+				{ "id":1, "name":"door", "x":16, "y":0, "width":16, "height":16,
+					"properties":[{ "name":"glyph", "type":"string", "value":"X" }, { "name":"channel", "type":"int", "value":2 }] },
+				{ "id":2, "name":"", "type":"E", "x":40, "y":24, "width":0, "height":0, "point":true },
+				{ "id":3, "name":"", "gid":2, "x":0, "y":32, "width":16, "height":16 }
+			] }
+		] }"#;
+
+	fn check_level(level: &TiledLevel) {
+		assert_eq!(level.size, uvec2(3, 2));
+
+		let tiles: Vec<(char, Vec2)> = level.tiles.iter().map(|t| (t.ty, t.pos)).collect();
+		assert_eq!(tiles, vec![
+			('#', vec2(0.0, 0.0)),
+			('^', vec2(64.0, 0.0)),
+			('/', vec2(32.0, 32.0)),
+			('#', vec2(64.0, 32.0)),
+			// objects are centred on a tile the way the level file puts them
+			('X', vec2(32.0, 0.0)),
+			('E', vec2(64.0, 32.0)),
+			('/', vec2(0.0, 32.0)),
+		]);
+
+		let props = |name: &str, value: &str| (name.to_string(), value.to_string());
+		assert_eq!(level.tiles[4].props, vec![props("name", "door"), props("glyph", "X"), props("channel", "2")]);
+		assert!(level.tiles[5].props.is_empty());
+	}
+
+	#[test]
+	fn tmx_tiles_and_objects_are_read() {
+		check_level(&read_tmx(TMX_MAP, GLYPHS, GRID_SIZE).unwrap());
+	}
+
+	#[test]
+	fn json_tiles_and_objects_are_read() {
+		check_level(&read_tiled_json(JSON_MAP, GLYPHS, GRID_SIZE).unwrap());
+	}
+
+	#[test]
+	fn other_encodings_are_refused() {
+		let tmx = TMX_MAP.replace(r#"<data encoding="csv">"#, r#"<data encoding="base64">"#);
+		assert!(read_tmx(&tmx, GLYPHS, GRID_SIZE).is_err());
+		let json = JSON_MAP.replace(r#""name":"walls","#, r#""name":"walls", "encoding":"base64","#);
+		assert!(read_tiled_json(&json, GLYPHS, GRID_SIZE).is_err());
+	}
+
+	#[test]
+	fn infinite_maps_are_refused() {
+		let tmx = TMX_MAP.replace(r#"infinite="0""#, r#"infinite="1""#);
+		assert!(read_tmx(&tmx, GLYPHS, GRID_SIZE).is_err());
+		let json = JSON_MAP.replace(r#""infinite":false"#, r#""infinite":true"#);
+		assert!(read_tiled_json(&json, GLYPHS, GRID_SIZE).is_err());
+
+		// chunks on their own are enough, the flag might not be there
+		let tmx = TMX_MAP.replace("\n1,0,3,\n0,2,2147483649\n", r#"<chunk x="0" y="0" width="3" height="2">1,0,3,0,2,2</chunk>"#);
+		assert!(read_tmx(&tmx, GLYPHS, GRID_SIZE).is_err());
+		let json = JSON_MAP.replace(r#""data":[1, 0, 3, 0, 2, 2147483649]"#,
+			r#""chunks":[{ "x":0, "y":0, "width":3, "height":2, "data":[1, 0, 3, 0, 2, 2] }]"#);
+		assert!(read_tiled_json(&json, GLYPHS, GRID_SIZE).is_err());
+	}
+}
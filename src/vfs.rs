@@ -0,0 +1,229 @@
+// every file the game reads goes through here, so it doesn't matter if the assets are loose
+// files or packed into an archive.
+//   loose files are looked for in the working directory first and then next to the executable
+//   assets.pak is used when there are no loose assets, building with --features embed_assets
+//   puts it inside the executable instead. cargo run --bin pack_assets makes it
+// a file that is in the archive is read from there even when there is a loose copy
+// level packs are folders in packs, laid out like assets with at least a Levels/Campaign.par.
+// the files of the active pack are used instead of the ones in assets with the same path
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use macroquad::prelude::*;
+
+pub const ARCHIVE_NAME: &str = "assets.pak";
+const ASSET_ROOT: &str = "assets";
+pub const PACK_FOLDER: &str = "packs";
+const PACK_CAMPAIGN: &str = "Levels/Campaign.par";
+const ARCHIVE_MAGIC: &[u8; 4] = b"GPAK";
+const ARCHIVE_VERSION: u32 = 1;
+
+static ROOT: OnceLock<PathBuf> = OnceLock::new();
+static ARCHIVE: OnceLock<Option<Archive>> = OnceLock::new();
+static ACTIVE_PACK: Mutex<Option<String>> = Mutex::new(None);
+
+// the archive is the magic, the version and the file count, then for every file the
+// length of its path, the path, the length of its data and the data. numbers are little endian u32
+pub struct Archive {
+	data: Cow<'static, [u8]>,
+	files: HashMap<String, (usize, usize)>,
+}
+
+impl Archive {
+	pub fn parse(data: Cow<'static, [u8]>) -> Result<Self, String> {
+		let mut files = HashMap::new();
+		{
+			let mut reader = ArchiveReader {data: &data, pos: 0};
+			if reader.take(4)? != ARCHIVE_MAGIC {
+				return Err(String::from("not an asset archive"));
+			}
+			let version = reader.read_u32()?;
+			if version != ARCHIVE_VERSION {
+				return Err(format!("archive version {} isn't supported", version));
+			}
+
+			let count = reader.read_u32()?;
+			for _ in 0..count {
+				let path_len = reader.read_u32()? as usize;
+				let path = String::from_utf8(reader.take(path_len)?.to_vec()).map_err(|_| String::from("file path isn't utf8"))?;
+				let len = reader.read_u32()? as usize;
+				let start = reader.pos;
+				reader.take(len)?;
+				files.insert(path, (start, len));
+			}
+		}
+		Ok(Self {data, files})
+	}
+
+	pub fn get(&self, path: &str) -> Option<&[u8]> {
+		self.files.get(path).map(|&(start, len)| &self.data[start..start + len])
+	}
+
+	#[allow(dead_code)]
+	pub fn get_paths(&self) -> Vec<&str> {
+		let mut paths: Vec<&str> = self.files.keys().map(|p| p.as_str()).collect();
+		paths.sort();
+		paths
+	}
+}
+
+struct ArchiveReader<'a> {
+	data: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> ArchiveReader<'a> {
+	fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+		let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len()).ok_or("archive ends too early")?;
+		let res = &self.data[self.pos..end];
+		self.pos = end;
+		Ok(res)
+	}
+
+	fn read_u32(&mut self) -> Result<u32, String> {
+		let bytes = self.take(4)?;
+		Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+	}
+}
+
+#[allow(dead_code)]
+pub fn write_archive(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+	let mut res = Vec::new();
+	res.extend_from_slice(ARCHIVE_MAGIC);
+	res.extend_from_slice(&ARCHIVE_VERSION.to_le_bytes());
+	res.extend_from_slice(&(files.len() as u32).to_le_bytes());
+	for (path, data) in files {
+		res.extend_from_slice(&(path.len() as u32).to_le_bytes());
+		res.extend_from_slice(path.as_bytes());
+		res.extend_from_slice(&(data.len() as u32).to_le_bytes());
+		res.extend_from_slice(data);
+	}
+	res
+}
+
+// finds the assets, has to be called before anything is loaded
+#[allow(dead_code)]
+pub fn mount() -> Result<(), String> {
+	let root = find_root();
+	let archive = load_archive(&root)?;
+	let _ = ROOT.set(root);
+	let _ = ARCHIVE.set(archive);
+	Ok(())
+}
+
+fn find_root() -> PathBuf {
+	if Path::new(ASSET_ROOT).is_dir() {
+		return PathBuf::new();
+	}
+	match std::env::current_exe().ok().and_then(|exe| exe.parent().map(|p| p.to_path_buf())) {
+		Some(exe_folder) if exe_folder.join(ASSET_ROOT).is_dir() || exe_folder.join(ARCHIVE_NAME).is_file() => exe_folder,
+		_ => PathBuf::new(),
+	}
+}
+
+#[cfg(feature = "embed_assets")]
+fn load_archive(_root: &Path) -> Result<Option<Archive>, String> {
+	static EMBEDDED: &[u8] = include_bytes!("../assets.pak");
+	Archive::parse(Cow::Borrowed(EMBEDDED)).map(Some).map_err(|e| format!("Embedded {}: {}", ARCHIVE_NAME, e))
+}
+
+#[cfg(not(feature = "embed_assets"))]
+fn load_archive(root: &Path) -> Result<Option<Archive>, String> {
+	let path = root.join(ARCHIVE_NAME);
+	if root.join(ASSET_ROOT).is_dir() || !path.is_file() {
+		return Ok(None);
+	}
+	let data = std::fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+	Archive::parse(Cow::Owned(data)).map(Some).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[allow(dead_code)]
+pub fn set_active_pack(pack: Option<String>) {
+	*ACTIVE_PACK.lock().unwrap() = pack;
+}
+
+#[allow(dead_code)]
+pub fn get_active_pack() -> Option<String> {
+	ACTIVE_PACK.lock().unwrap().clone()
+}
+
+// every folder in packs with a campaign, loose or in the archive
+#[allow(dead_code)]
+pub fn find_packs() -> Vec<String> {
+	let mut packs = Vec::new();
+	if let Ok(entries) = std::fs::read_dir(root_path(PACK_FOLDER)) {
+		for entry in entries.flatten() {
+			if entry.path().join(PACK_CAMPAIGN).is_file() {
+				packs.push(entry.file_name().to_string_lossy().to_string());
+			}
+		}
+	}
+	if let Some(archive) = ARCHIVE.get().and_then(|a| a.as_ref()) {
+		for path in archive.files.keys() {
+			let pack = path.strip_prefix(PACK_FOLDER).and_then(|p| p.strip_prefix('/'))
+				.and_then(|p| p.strip_suffix(PACK_CAMPAIGN))
+				.and_then(|p| p.strip_suffix('/'));
+			if let Some(pack) = pack.filter(|p| !p.contains('/')) {
+				packs.push(pack.to_string());
+			}
+		}
+	}
+	packs.sort();
+	packs.dedup();
+	packs
+}
+
+// where the active pack keeps its version of a file in assets
+fn pack_path(path: &str) -> Option<String> {
+	let pack = ACTIVE_PACK.lock().unwrap().clone()?;
+	let rest = path.strip_prefix(ASSET_ROOT)?.strip_prefix('/')?;
+	Some(format!("{}/{}/{}", PACK_FOLDER, pack, rest))
+}
+
+// the path on disk without looking at packs
+pub fn root_path(path: &str) -> String {
+	match ROOT.get() {
+		Some(root) if !root.as_os_str().is_empty() => root.join(path).to_string_lossy().to_string(),
+		_ => path.to_string(),
+	}
+}
+
+// where a loose file is on disk, for the things that write files or look at them directly
+pub fn disk_path(path: &str) -> String {
+	match pack_path(path).map(|p| root_path(&p)) {
+		Some(pack_file) if Path::new(&pack_file).is_file() => pack_file,
+		_ => root_path(path),
+	}
+}
+
+pub async fn load_file(path: &str) -> Result<Vec<u8>, macroquad::Error> {
+	let archive = ARCHIVE.get().and_then(|a| a.as_ref());
+	if let Some(pack_file) = pack_path(path) {
+		if let Some(data) = archive.and_then(|a| a.get(&pack_file)) {
+			return Ok(data.to_vec());
+		}
+		// most files aren't replaced by the pack, so failing here just means using the base one
+		if let Ok(data) = macroquad::file::load_file(&root_path(&pack_file)).await {
+			return Ok(data);
+		}
+	}
+
+	if let Some(data) = archive.and_then(|a| a.get(path)) {
+		return Ok(data.to_vec());
+	}
+	macroquad::file::load_file(&root_path(path)).await
+}
+
+pub async fn load_string(path: &str) -> Result<String, macroquad::Error> {
+	let data = load_file(path).await?;
+	Ok(String::from_utf8_lossy(&data).to_string())
+}
+
+pub async fn load_texture(path: &str) -> Result<Texture2D, macroquad::Error> {
+	let data = load_file(path).await?;
+	let image = Image::from_file_with_format(&data, None)?;
+	Ok(Texture2D::from_image(&image))
+}
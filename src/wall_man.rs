@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use macroquad::prelude::*;
 
 use crate::collision::*;
@@ -5,7 +7,13 @@ use crate::go_helpers::*;
 
 pub struct WallMan<TTile> where TTile: Clone{
 	pub wall_colliders: Vec<MultiCollider<CollBox>>,
+	// one way platforms, in a grid laid out like the walls
+	pub platform_colliders: Vec<MultiCollider<CollBox>>,
+	pub platforms: Vec<CollBox>,
+	pub slopes: Vec<CollSlope>,
 	wall_sprites: Vec<(Vec2, TTile)>,
+	// walls waiting for build_colliders to merge them
+	wall_boxes: Vec<CollBox>,
 
 	placeholder: TTile
 }
@@ -13,42 +21,57 @@ pub struct WallMan<TTile> where TTile: Clone{
 impl <TTile> WallMan<TTile> where TTile:Clone{
 	pub fn new(wall_mc: Vec<MultiCollider<CollBox>>, tile_placeholder: TTile) -> Self {
 		Self{
+			platform_colliders: wall_mc.iter().map(|mc| MultiCollider::new(mc.main)).collect(),
 			wall_colliders: wall_mc,
+			platforms: Vec::new(),
+			slopes: Vec::new(),
 			wall_sprites: Vec::new(),
+			wall_boxes: Vec::new(),
 			placeholder: tile_placeholder
 		}		
 	}
 
+	// the wall only collides once build_colliders is called
 	pub fn add_wall(&mut self, rect: CollBox) {
-		MultiCollider::add_if_intersect_slice(self.wall_colliders.as_mut_slice(), &rect);
+		self.wall_boxes.push(rect);
 		self.wall_sprites.push((rect.pos, self.placeholder.clone()));
 	}
 
-	pub fn assign_tile_types <F>(&mut self, f:F, check_dist: Vec2, coll_dim: Vec2, world_dim: Option<Vec2>) where F: Fn(IVec2, BVec2)-> TTile {
-		for i in 0..self.wall_sprites.len() {
-			let mut exposure = ivec2(0, 0);
-			let mut collided = BVec2::new(false, false);
-			for j in [-1.0, 1.0].iter() {
-				let (pos, _) = self.wall_sprites[i].clone();
-				let check_pos = pos + vec2(check_dist.x * j.clone(), 0.0);
-				let in_world = if let Some(dim) = world_dim {
-					0.0 <= check_pos.x && check_pos.x <= dim.x
-				}else {true};
-				if MultiCollider::check_intersection_slice(self.wall_colliders.as_slice(), &collbox!(check_pos, coll_dim)) || !in_world {
-					exposure.x += j.clone() as i32;
-					collided.x = true;
-				}
+	pub fn add_platform(&mut self, rect: CollBox) {
+		MultiCollider::add_if_intersect_slice(self.platform_colliders.as_mut_slice(), &rect);
+		self.platforms.push(rect);
+	}
+
+	pub fn add_slope(&mut self, slope: CollSlope) {
+		self.slopes.push(slope);
+	}
 
-				let check_pos: Vec2 = pos + vec2(0.0, check_dist.y * j.clone());
-				let in_world = if let Some(dim) = world_dim {
-					0.0 <= check_pos.y && check_pos.y <= dim.y
-				}else {true};
-				if MultiCollider::check_intersection_slice(self.wall_colliders.as_slice(), &collbox!(check_pos, coll_dim))  || !in_world {
-					exposure.y += j.clone() as i32;
-					collided.y = true;
+	// merges the walls into as few boxes as it can and puts them in the collision grid,
+	// so there are no seams between walls to catch on and less to check
+	pub fn build_colliders(&mut self) {
+		for rect in merge_wall_boxes(&self.wall_boxes) {
+			MultiCollider::add_if_intersect_slice(self.wall_colliders.as_mut_slice(), &rect);
+		}
+		self.wall_boxes.clear();
+	}
+
+	// picks every wall's tile from the walls around it, outside the world and slopes count as wall
+	pub fn autotile(&mut self, tiler: &AutoTiler<TTile>, tile_size: f32, world_dim: Option<Vec2>) {
+		let cells: HashSet<IVec2> = self.wall_sprites.iter().map(|(pos, _)| *pos)
+			.chain(self.slopes.iter().map(|slope| slope.cbox.pos))
+			.map(|pos| (pos / tile_size).round().as_ivec2()).collect();
+		for i in 0..self.wall_sprites.len() {
+			let cell = (self.wall_sprites[i].0 / tile_size).round().as_ivec2();
+			let mut mask = 0u8;
+			for (bit, offset) in NEIGHBOURS.iter().enumerate() {
+				let neighbour = cell + *offset;
+				let pos = neighbour.as_vec2() * tile_size;
+				let in_world = world_dim.is_none_or(|dim| pos.cmpge(Vec2::ZERO).all() && pos.cmple(dim).all());
+				if cells.contains(&neighbour) || !in_world {
+					mask |= 1 << bit;
 				}
 			}
-			self.wall_sprites[i] = (self.wall_sprites[i].0, f(exposure, collided));
+			self.wall_sprites[i].1 = tiler.pick(mask);
 		}
 	}
 
@@ -59,6 +82,7 @@ impl <TTile> WallMan<TTile> where TTile:Clone{
 				self.wall_colliders.remove(i);
 			}
 		}
+		self.platform_colliders.retain(|mc| !mc.read_multi().is_empty());
 		
 		println!("After cull: {}", self.wall_colliders.len());
 	}
@@ -67,7 +91,13 @@ impl <TTile> WallMan<TTile> where TTile:Clone{
 		for i in 0..self.wall_colliders.len() {
 			self.wall_colliders[i].clear();
 		}
+		for mc in self.platform_colliders.iter_mut() {
+			mc.clear();
+		}
+		self.platforms.clear();
+		self.slopes.clear();
 		self.wall_sprites.clear();
+		self.wall_boxes.clear();
 	}
 
 	pub fn draw_walls <F> (&self, f: F) where F: Fn(Vec2, &TTile) {
@@ -78,3 +108,109 @@ impl <TTile> WallMan<TTile> where TTile:Clone{
 	}
 }
 
+
+// the 8 tiles around a wall in reading order, neighbour n is bit n of a neighbour mask
+pub const NEIGHBOURS: [IVec2; 8] = [
+	ivec2(-1, -1), ivec2(0, -1), ivec2(1, -1),
+	ivec2(-1, 0), ivec2(1, 0),
+	ivec2(-1, 1), ivec2(0, 1), ivec2(1, 1),
+];
+
+struct TileRule<TTile> {
+	walls: u8,
+	empty: u8,
+	tile: TTile,
+}
+
+// rules for which tile a wall gets, the first rule that fits is used
+pub struct AutoTiler<TTile> {
+	rules: Vec<TileRule<TTile>>,
+	default: TTile,
+}
+
+impl <TTile> AutoTiler<TTile> where TTile: Clone {
+	pub fn new(default: TTile) -> Self {
+		Self {
+			rules: Vec::new(),
+			default,
+		}
+	}
+
+	// the pattern is the 3 by 3 block around the wall row by row, rows can be split with /.
+	// # is a wall, - is empty and ? is either. the middle is the wall itself and is skipped
+	pub fn add_rule(&mut self, pattern: &str, tile: TTile) -> Result<(), String> {
+		let cells: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace() && *c != '/').collect();
+		if cells.len() != 9 {
+			return Err(format!("{} should have 9 tiles", pattern));
+		}
+
+		let mut rule = TileRule {walls: 0, empty: 0, tile};
+		for (bit, c) in cells.iter().take(4).chain(cells.iter().skip(5)).enumerate() {
+			match c {
+				'#' => rule.walls |= 1 << bit,
+				'-' => rule.empty |= 1 << bit,
+				'?' => {}
+				c => return Err(format!("{} in {} isn't #, - or ?", c, pattern)),
+			}
+		}
+		self.rules.push(rule);
+		Ok(())
+	}
+
+	pub fn pick(&self, mask: u8) -> TTile {
+		self.rules.iter()
+			.find(|r| mask & r.walls == r.walls && mask & r.empty == 0)
+			.map_or(self.default.clone(), |r| r.tile.clone())
+	}
+}
+
+// greedy rectangle merging. walls the same size as the first one are put on a grid and grown
+// right as far as they go and then down as long as the whole row below is wall.
+// walls of other sizes are left as they are
+pub fn merge_wall_boxes(boxes: &[CollBox]) -> Vec<CollBox> {
+	let Some(first) = boxes.first() else {return Vec::new();};
+	let half_dim = first.half_dim;
+	let size = half_dim * 2.0;
+
+	let mut res = Vec::new();
+	let mut cells = HashSet::new();
+	for rect in boxes {
+		if rect.half_dim == half_dim {
+			cells.insert((rect.pos / size).round().as_ivec2());
+		} else {
+			res.push(*rect);
+		}
+	}
+
+	// goes through the cells top to bottom and left to right so the merging is the same every time
+	let mut sorted: Vec<IVec2> = cells.iter().copied().collect();
+	sorted.sort_by_key(|c| (c.y, c.x));
+
+	let mut used = HashSet::new();
+	for start in sorted {
+		if used.contains(&start) {
+			continue;
+		}
+		let free = |cell: IVec2| cells.contains(&cell) && !used.contains(&cell);
+
+		let mut width = 1;
+		while free(start + ivec2(width, 0)) {
+			width += 1;
+		}
+		let mut height = 1;
+		while (0..width).all(|x| free(start + ivec2(x, height))) {
+			height += 1;
+		}
+
+		for y in 0..height {
+			for x in 0..width {
+				used.insert(start + ivec2(x, y));
+			}
+		}
+
+		let span = ivec2(width, height).as_vec2();
+		let min = start.as_vec2() * size - half_dim;
+		res.push(CollBox::new(min + span * half_dim, span * half_dim));
+	}
+	res
+}